@@ -0,0 +1,171 @@
+use std::io;
+
+use thiserror::Error;
+
+// Errors returned by the vault operations, the CLI decides how to color them
+#[derive(Debug, Error)]
+pub enum CodevaultError {
+    // No snippet in the collection has the requested ID
+    #[error("snippet ID '{0}' does not exist in the collection")]
+    NotFound(u32),
+
+    // Several of the requested IDs are not in the collection
+    #[error("snippet IDs '{}' do not exist in the collection", join_ids(.0))]
+    NotFoundMany(Vec<u32>),
+
+    // An ID given on the command line is not a number
+    #[error("invalid snippet ID '{0}'")]
+    InvalidId(String),
+
+    // A tag given on the command line is empty or contains a comma
+    #[error("invalid tag '{0}'")]
+    InvalidTag(String),
+
+    // A required option was left out and can't be asked for, because stdin is not a terminal or --quiet is set
+    #[error("missing {0}, it can only be asked for in a terminal without --quiet")]
+    MissingArgument(&'static str),
+
+    // The command needs a snippet ID but none was given
+    #[error("missing snippet ID")]
+    MissingId,
+
+    // The command needs a snippet ID or tag but neither was given
+    #[error("missing snippet ID or tag")]
+    MissingSelector,
+
+    // The tag and/or language selectors match no snippet
+    #[error("no snippets match '{0}' in the collection")]
+    NoMatch(String),
+
+    // The language is not known to syntect, with the closest known name if there is one
+    #[error("unsupported language '{language}'{}", suggestion.as_ref().map(|s| format!(", did you mean '{}'?", s)).unwrap_or_default())]
+    UnsupportedLanguage {
+        language: String,
+        suggestion: Option<String>,
+    },
+
+    // The theme is not one of syntect's default themes
+    #[error("unknown theme '{theme}', available themes: {available}")]
+    UnknownTheme { theme: String, available: String },
+
+    // The command writes a single snippet but the selectors match several
+    #[error("{0} snippets match, select a single one or use --format markdown")]
+    MultipleMatches(usize),
+
+    // The pattern given to `view --grep` is not a valid regular expression
+    #[error("invalid pattern '{pattern}': {message}")]
+    InvalidPattern { pattern: String, message: String },
+
+    // The diff command compares exactly two snippets
+    #[error("diff compares two snippets, pass exactly two IDs instead of {0}")]
+    DiffNeedsTwoIds(usize),
+
+    // A snippet can't be merged into itself
+    #[error("snippet {0} can't be merged into itself")]
+    MergeIntoItself(u32),
+
+    // The snippet's history has no version with this number
+    #[error("snippet {id} has no version {version}, run 'codevault history -i {id}' to list them")]
+    VersionNotFound { id: u32, version: usize },
+
+    // The JSON given to import is malformed or has an entry that is not a valid snippet
+    #[error("can't import '{origin}': {message}")]
+    InvalidImport { origin: String, message: String },
+
+    // The command needs at least one snippet but the collection is empty
+    #[error("the collection is empty, capture a snippet first")]
+    EmptyCollection,
+
+    // Undo was asked for but nothing has been deleted
+    #[error("the trash is empty, there is nothing to restore")]
+    EmptyTrash,
+
+    // The system clipboard could not be opened, e.g. when no display is available
+    #[error("the clipboard is not available: {0}")]
+    Clipboard(String),
+
+    // A confirmation is needed but can't be asked for because stdin is not a terminal
+    #[error("confirmation needed but stdin is not a terminal, pass --yes to confirm")]
+    ConfirmationRequired,
+
+    // The config file is not valid TOML or has an unknown setting or theme
+    #[error("config file '{path}': {message}")]
+    Config { path: String, message: String },
+
+    // No capture template has this name in the config file
+    #[error("no template named '{0}', run 'codevault template list' to see them")]
+    TemplateNotFound(String),
+
+    // There is no config file to save templates in, because the system has no config directory
+    #[error("no config directory on this system, choose a config file with --config")]
+    NoConfigPath,
+
+    // The external editor could not be started or did not exit cleanly
+    #[error("{0}")]
+    Editor(String),
+
+    // Reading or writing a file failed
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+
+    // A data or trash file is not valid JSON for its content
+    #[error("reading '{path}': {source}")]
+    Deserialize {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    // The snippets could not be turned into JSON
+    #[error("serializing snippets: {0}")]
+    Serialize(#[source] serde_json::Error),
+
+    // The passphrase is missing or wrong, or the encrypted file is damaged
+    #[error("{0}")]
+    Encryption(String),
+}
+
+impl CodevaultError {
+    // Build a `map_err` adapter wrapping an I/O error with what was being done, e.g. "creating file"
+    pub fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> CodevaultError {
+        let context = context.into();
+        move |source| CodevaultError::Io { context, source }
+    }
+}
+
+// Function to list IDs the way they are typed on the command line, e.g. "3, 7"
+fn join_ids(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_plain_text() {
+        assert_eq!(
+            CodevaultError::NotFound(7).to_string(),
+            "snippet ID '7' does not exist in the collection"
+        );
+        assert_eq!(
+            CodevaultError::NotFoundMany(vec![3, 7]).to_string(),
+            "snippet IDs '3, 7' do not exist in the collection"
+        );
+        assert_eq!(
+            CodevaultError::UnsupportedLanguage {
+                language: "pyhton".to_string(),
+                suggestion: Some("Python".to_string()),
+            }
+            .to_string(),
+            "unsupported language 'pyhton', did you mean 'Python'?"
+        );
+    }
+}
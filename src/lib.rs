@@ -0,0 +1,4095 @@
+// Core of the snippet vault: storage, display and the operations behind each command.
+// The `codevault` binary is a thin clap front end over these functions.
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::Style;
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use chrono::{DateTime, Local};
+use dialoguer::FuzzySelect;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use serde::Serialize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub mod error;
+pub mod models;
+pub mod style;
+use error::CodevaultError;
+use models::edit_distance;
+use models::expand_tabs;
+use models::line_comment;
+use models::mark_terms;
+use models::parse_tags;
+use models::resolve_language;
+use models::strip_ansi_codes;
+use models::wrap_ansi_line;
+use models::Snippet;
+use models::TagTree;
+use models::Template;
+use models::TrashEntry;
+use models::{color_enabled, compact, is_encrypted, paint, spaced, AppendRecord, Config, Context};
+use models::{
+    format_timestamp, DisplayOptions, EditFields, EditOutcome, ExportFormat, ExportOptions,
+};
+use models::{syntax_set, theme_set};
+use models::{ImportSummary, SnippetField, SortKey, TagCase, ViewFilter};
+
+// Indentation of the continuation segments of a wrapped code line
+const WRAP_INDENT: usize = 2;
+// Widest a box is drawn when stdout is not a terminal
+const DEFAULT_BOX_WIDTH: usize = 100;
+// Maximum number of deleted snippets kept in the trash file
+const TRASH_LIMIT: usize = 50;
+// How often the clipboard is checked while watching it
+const CLIPBOARD_POLL: Duration = Duration::from_millis(500);
+
+// Collections from this size on take noticeably long to read and write on every command
+const LARGE_VAULT: usize = 5000;
+
+pub fn generate_unique_id(ctx: &Context, file_path: &str) -> Result<u32, CodevaultError> {
+    // A missing file means the collection is empty, so start with ID 1
+    if !Path::new(file_path).exists() {
+        return Ok(1);
+    }
+
+    // Load the existing snippets, a malformed file is reported instead of silently resetting IDs
+    let snippets = load_snippets(ctx, file_path)?;
+
+    // Return the next unique ID by incrementing the maximum ID found
+    Ok(snippets.iter().map(|s| s.id).max().unwrap_or(0) + 1)
+}
+
+// Function to find the next unique ID for `capture --append-only` from the records' ID fields,
+// without building the snippets. Falls back to generate_unique_id when the file is missing,
+// encrypted or not a JSON array of snippets.
+pub fn generate_append_id(ctx: &Context, file_path: &str) -> Result<u32, CodevaultError> {
+    match read_append_records(ctx, file_path) {
+        Some(records) => Ok(records.iter().map(|record| record.id).max().unwrap_or(0) + 1),
+        None => generate_unique_id(ctx, file_path),
+    }
+}
+
+// Function to read only the ID and code of each record in the data file, for `capture
+// --append-only`. None when the file is missing, encrypted or not a JSON array of snippets.
+fn read_append_records(ctx: &Context, file_path: &str) -> Option<Vec<AppendRecord>> {
+    if ctx.encryption_enabled() {
+        return None;
+    }
+    let content = std::fs::read(file_path).ok()?;
+    if is_encrypted(&content) {
+        return None;
+    }
+    // A blank file, e.g. one just created with `touch`, holds no snippets yet
+    if content.trim_ascii().is_empty() {
+        return Some(Vec::new());
+    }
+    serde_json::from_slice(&content).ok()
+}
+
+// Function to give the blank line left below each box, none in compact mode
+fn box_gap() -> &'static str {
+    if compact() {
+        ""
+    } else {
+        "\n"
+    }
+}
+
+// Function to build the star marker shown next to the ID of pinned snippets
+fn pin_marker(snippet: &Snippet) -> String {
+    if snippet.pinned {
+        format!(" {}", style::label("★"))
+    } else {
+        String::new()
+    }
+}
+
+// Function to format a "Name: value" line of a snippet box
+fn detail_line(name: &str, value: impl std::fmt::Display) -> String {
+    format!(
+        "  {} {}",
+        style::label(format!("{}:", name)),
+        style::value(value)
+    )
+}
+
+// Function to format the language line of a snippet box, each language gets its own color
+fn language_line(language: Option<&str>) -> String {
+    let language = match language {
+        Some(language) => style::language(language),
+        None => style::value("plain text"),
+    };
+    format!("  {} {}", style::label("Language:"), language)
+}
+
+// Function to build a border line of the given width using the provided corner and fill characters
+fn border_line(left: &str, fill: &str, right: &str, width: usize) -> String {
+    style::border(format!("{}{}{}", left, fill.repeat(width), right))
+}
+
+fn format_with_border(content: &str, width: usize) -> String {
+    // Remove ANSI color codes from content to calculate the width correctly
+    let stripped_content = strip_ansi_codes(content);
+
+    // Calculate the amount of padding needed to make the total width equal to 'width'
+    // Wide characters such as CJK and emoji take two terminal cells
+    let padding = width.saturating_sub(stripped_content.width());
+
+    // Format content with borders and padding to fit the specified width
+    paint(&format!(
+        "{}{}{}{}",
+        style::border("║"),
+        content,
+        " ".repeat(padding),
+        style::border("║")
+    ))
+}
+
+// Function to format the code section of a snippet box, one bordered line per code line or wrapped segment
+fn format_code_lines(
+    code: &str,
+    language: &Option<String>,
+    theme: Option<&str>,
+    width: usize,
+    display: &DisplayOptions,
+    code_room: Option<usize>,
+) -> Vec<String> {
+    // Highlight the code if a language is specified, otherwise use the plain code
+    let highlighted_code = if let Some(lang) = language {
+        highlight_code_snippets(code, lang, theme, &display.theme)
+    } else {
+        code.to_string()
+    };
+
+    // Start with the header for the code section with a border
+    let mut lines = vec![format_with_border(
+        &format!("  {}", style::label("Code:")),
+        width,
+    )];
+
+    // Add each line of the highlighted code with a border
+    lines.extend(
+        code_body_lines(code, &highlighted_code, display, code_room)
+            .iter()
+            .map(|line| format_with_border(&format!("  {}", line), width)),
+    );
+
+    lines
+}
+
+// Function to lay out highlighted code line by line, prefixed by a dimmed line number if requested
+// and wrapped to the room given, if any
+fn code_body_lines(
+    code: &str,
+    highlighted_code: &str,
+    display: &DisplayOptions,
+    code_room: Option<usize>,
+) -> Vec<String> {
+    let line_numbers = display.line_numbers;
+    let mut lines = Vec::new();
+
+    // Width of the right-aligned line numbers, based on the number of the last line
+    let number_width = code.lines().count().to_string().len();
+
+    for (index, line) in highlighted_code.lines().enumerate() {
+        // Mark the searched terms before wrapping, so a match cut by the wrap stays marked
+        let line = if color_enabled() && !display.highlight_terms.is_empty() {
+            mark_terms(line, &display.highlight_terms)
+        } else {
+            line.to_string()
+        };
+        let line = line.as_str();
+
+        // Wrap the line if it doesn't fit, continuation segments get a hanging indent
+        let segments = match code_room {
+            Some(room) => wrap_ansi_line(line, room, room.saturating_sub(WRAP_INDENT)),
+            None => vec![line.to_string()],
+        };
+
+        for (segment_index, segment) in segments.iter().enumerate() {
+            let gutter = match (line_numbers, segment_index) {
+                (false, _) => String::new(),
+                (true, 0) => format!("\x1b[2m{:>width$}\x1b[0m ", index + 1, width = number_width),
+                (true, _) => " ".repeat(number_width + 1),
+            };
+            let indent = if segment_index == 0 {
+                String::new()
+            } else {
+                " ".repeat(WRAP_INDENT)
+            };
+            lines.push(format!("{}{}{}", gutter, indent, segment));
+        }
+    }
+
+    lines
+}
+
+// Function to detect the width of the terminal stdout is attached to
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+// Function to print output through the user's pager when it is longer than the terminal is tall.
+// The pager is taken from $PAGER and defaults to `less -R`, so the colors survive.
+pub fn page_output(output: &str, use_pager: bool) {
+    let fits = terminal_size::terminal_size()
+        .is_none_or(|(_, terminal_size::Height(height))| output.lines().count() < height as usize);
+    if !use_pager || fits || !io::stdout().is_terminal() {
+        print!("{}", output);
+        return;
+    }
+
+    // An empty $PAGER or `cat` asks for no paging at all
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut words = pager.split_whitespace();
+    let Some(program) = words.next().filter(|program| *program != "cat") else {
+        print!("{}", output);
+        return;
+    };
+
+    // Let a plain `less` keep the colors and quit on short output, like git does
+    let mut command = Command::new(program);
+    command.args(words).stdin(std::process::Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    // Fall back to printing directly when the pager can't be started
+    let Ok(mut child) = command.spawn() else {
+        print!("{}", output);
+        return;
+    };
+
+    // The pager may be quit before reading everything, which closes the pipe early
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(output.as_bytes());
+    }
+    let _ = child.wait();
+}
+
+// Function to get the widest a box may be drawn, the terminal width or a fixed default when not on a terminal
+fn box_width_limit() -> usize {
+    terminal_width().unwrap_or(DEFAULT_BOX_WIDTH)
+}
+
+// Function to cut plain text to at most the given terminal width, ending it with an ellipsis when cut
+fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    // Keep room for the ellipsis, wide characters take two cells
+    let mut truncated = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        if used + ch_width + 1 > width {
+            break;
+        }
+        truncated.push(ch);
+        used += ch_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+// Function to wrap a detail line of a box to the given width, continuation lines are indented under the label
+fn wrap_detail_line(line: &str, width: usize) -> Vec<String> {
+    let indent = " ".repeat(2 + WRAP_INDENT);
+    wrap_ansi_line(line, width, width.saturating_sub(indent.len()))
+        .into_iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            if index == 0 {
+                segment
+            } else {
+                format!("{}{}", indent, segment)
+            }
+        })
+        .collect()
+}
+
+// Function to compute the width of the line number gutter, including the separating space
+fn line_number_gutter(code: &str, line_numbers: bool) -> usize {
+    if line_numbers {
+        code.lines().count().to_string().len() + 1
+    } else {
+        0
+    }
+}
+
+// Function to check whether a snippet is a Markdown note that should be shown formatted
+fn renders_as_markdown(snippet: &Snippet, display: &DisplayOptions) -> bool {
+    display.render_markdown
+        && snippet
+            .language
+            .as_deref()
+            .and_then(resolve_language)
+            .is_some_and(|syntax| syntax.name == "Markdown")
+}
+
+// Function to format Markdown for the terminal, with headings, emphasis, lists and tables drawn
+// instead of their markup, wrapped to the given width if there is one
+fn render_markdown(markdown: &str, width: Option<usize>) -> String {
+    // Headings are centered by default, keep them at the left like the rest of the box
+    let mut skin = termimad::MadSkin::default();
+    for header in skin.headers.iter_mut() {
+        header.align = termimad::Alignment::Left;
+    }
+    let rendered = skin.text(markdown, width).to_string();
+
+    // The formatting relies on colors, without them only the layout is kept
+    let rendered = if color_enabled() {
+        rendered
+    } else {
+        strip_ansi_codes(&rendered)
+    };
+    rendered
+        .lines()
+        .map(|line| line.trim_end().to_string() + "\n")
+        .collect()
+}
+
+// Function to highlight code in the given theme, or the default one when there is none
+pub fn highlight_code_snippets(
+    code: &str,
+    language: &str,
+    theme: Option<&str>,
+    default_theme: &str,
+) -> String {
+    // Skip highlighting entirely when colors are disabled
+    if !color_enabled() {
+        return code.to_string();
+    }
+
+    // The default syntaxes and themes are loaded once and shared by every snippet
+    let ps = syntax_set();
+    let ts = theme_set();
+
+    // Find the syntax definition for the language, accepting aliases like "cpp" or "py"
+    let syntax = resolve_language(language)
+        .and_then(|syntax| ps.find_syntax_by_name(&syntax.name))
+        .unwrap_or(ps.find_syntax_plain_text());
+
+    let mut output = String::new();
+
+    // One highlighter for the whole snippet, so constructs spanning lines like block comments
+    // and multi-line strings keep their colors
+    // A snippet's theme that is no longer known falls back to the default one
+    let theme = theme
+        .and_then(|theme| ts.themes.get(theme))
+        .unwrap_or(&ts.themes[default_theme]);
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    // Iterate through each line of the code with its endings
+    for line in LinesWithEndings::from(code) {
+        // Highlight the current line, collecting style and text tuples
+        let ranges: Vec<(Style, &str)> = highlighter.highlight_line(line, ps).unwrap();
+
+        // Format the highlighted line into terminal color codes
+        let escaped_line = format_terminal_snippets(&ranges);
+
+        // Append the formatted line to the output
+        output.push_str(&escaped_line);
+    }
+
+    output
+}
+
+fn format_terminal_snippets(v: &[(Style, &str)]) -> String {
+    let mut s = String::new();
+    let mut line_ending = String::new();
+
+    // Iterate through each style-text pair
+    for &(ref style, text) in v.iter() {
+        // Keep the line ending out of the colored text, so the background doesn't bleed into the next line
+        let content = text.trim_end_matches(['\r', '\n']);
+        line_ending.push_str(&text[content.len()..]);
+        if content.is_empty() {
+            continue;
+        }
+
+        // Format text with ANSI color codes based on the style's foreground and background
+        let (fg, bg) = (style.foreground, style.background);
+        s.push_str(&format!(
+            "\x1b[38;2;{};{};{};48;2;{};{};{}m{}",
+            fg.r, fg.g, fg.b, bg.r, bg.g, bg.b, content
+        ));
+    }
+
+    // Reset ANSI color codes to default before the line ends
+    s.push_str("\x1b[0m");
+    s.push_str(&line_ending);
+
+    s
+}
+
+pub fn print_snippet(snippet: &Snippet, display: &DisplayOptions) {
+    print!("{}", render_snippet(snippet, display));
+}
+
+// Function to build the full box of a snippet as it is printed, so it can be paged as a whole
+pub fn render_snippet(snippet: &Snippet, display: &DisplayOptions) -> String {
+    // Format the chosen detail lines in the requested order, skipping the ones without a value
+    let detail_lines: Vec<String> = display
+        .fields
+        .iter()
+        .filter_map(|field| match field {
+            // The ID line marks pinned snippets
+            SnippetField::Id => Some(detail_line("ID", snippet.id) + &pin_marker(snippet)),
+            SnippetField::Tag => Some(detail_line("Snippet's Tag", snippet.tags_display())),
+            SnippetField::Language => Some(language_line(snippet.language.as_deref())),
+            SnippetField::Created => {
+                Some(detail_line("Created", format_timestamp(&snippet.timestamp)))
+            }
+            // Only snippets that have been edited have a modification time
+            SnippetField::Modified => snippet
+                .modified
+                .as_ref()
+                .map(|modified| detail_line("Modified", format_timestamp(modified))),
+            SnippetField::Description => snippet
+                .description
+                .as_ref()
+                .map(|desc| detail_line("Description", desc)),
+            // Where the code came from, if it was recorded
+            SnippetField::Source => snippet
+                .source
+                .as_ref()
+                .map(|source| detail_line("Source", source)),
+            // The code has its own section below the details
+            SnippetField::Code => None,
+        })
+        .collect();
+    let show_code = display.fields.contains(&SnippetField::Code);
+
+    // Expand tabs before anything is measured, the stored code keeps its tabs
+    let code = expand_tabs(&snippet.code, display.tab_width);
+
+    // Room left for code on each line when wrapping to the terminal, next to borders, indent and gutter
+    let code_room = |gutter: usize| {
+        display
+            .wrap
+            .then(|| box_width_limit().saturating_sub(8 + gutter).max(20))
+    };
+
+    // Show Markdown notes formatted when asked to, instead of highlighting their source
+    let (code, language) = if renders_as_markdown(snippet, display) {
+        (render_markdown(&code, code_room(0)), None)
+    } else {
+        (code, snippet.language.clone())
+    };
+
+    // Account for the line number gutter so the border lines up with the widest code line
+    let gutter = line_number_gutter(&code, display.line_numbers);
+
+    let code_room = code_room(gutter);
+
+    // Widest the box content may get, wrapped boxes always fit in the terminal
+    let content_limit = code_room.map(|room| room + gutter + 2);
+
+    // Wrap the detail lines that don't fit
+    let detail_lines: Vec<String> = detail_lines
+        .iter()
+        .flat_map(|line| match content_limit {
+            Some(limit) => wrap_detail_line(line, limit),
+            None => vec![line.clone()],
+        })
+        .collect();
+
+    // Collect all lines into a vector, stripping ANSI color codes for length calculation
+    let code_lines = if show_code { code.lines() } else { "".lines() };
+    let all_lines = detail_lines
+        .iter()
+        .map(|line| strip_ansi_codes(line))
+        .chain(code_lines.map(|line| {
+            // Wrapped code lines never take up more than the room available in the terminal
+            let line = strip_ansi_codes(line);
+            let visible: String = match code_room {
+                Some(room) => wrap_ansi_line(&line, room, room).swap_remove(0),
+                None => line,
+            };
+            " ".repeat(gutter) + &visible
+        }))
+        // The code section starts with its label, which may be wider than very short code
+        .chain(show_code.then(|| "Code:".to_string()))
+        .collect::<Vec<_>>();
+
+    // Determine the maximum line length for formatting
+    let max_line_length = all_lines.iter().map(|line| line.width()).max().unwrap_or(0);
+
+    // Set the width for the formatted output, adding extra space for borders
+    let adjusted_width = max_line_length + 4;
+
+    // Start with the top border of the snippet box
+    let mut lines = vec![border_line("╔", "═", "╗", adjusted_width)];
+
+    // Add each formatted line within the border
+    for line in &detail_lines {
+        lines.push(format_with_border(line, adjusted_width));
+    }
+
+    if show_code {
+        // Add a separator line within the snippet box when there are details above the code
+        if !detail_lines.is_empty() {
+            lines.push(border_line("╟", "─", "╢", adjusted_width));
+        }
+
+        // Add the code inside the snippet box with formatting
+        lines.extend(format_code_lines(
+            &code,
+            &language,
+            snippet.theme.as_deref(),
+            adjusted_width,
+            display,
+            code_room,
+        ));
+    }
+
+    // Finish with the bottom border of the snippet box
+    lines.push(border_line("╚", "═", "╝", adjusted_width) + box_gap());
+
+    lines.join("\n") + "\n"
+}
+
+// Function to render only the code of a snippet, without a box or details, for reading through many snippets
+pub fn render_code_only(snippet: &Snippet, display: &DisplayOptions) -> String {
+    let code = expand_tabs(&snippet.code, display.tab_width);
+    let code_room = |gutter: usize| {
+        display
+            .wrap
+            .then(|| box_width_limit().saturating_sub(gutter).max(20))
+    };
+
+    // Markdown notes can be shown formatted here too
+    let (code, highlighted) = if renders_as_markdown(snippet, display) {
+        let rendered = render_markdown(&code, code_room(0));
+        (rendered.clone(), rendered)
+    } else {
+        let highlighted = match &snippet.language {
+            Some(language) => {
+                highlight_code_snippets(&code, language, snippet.theme.as_deref(), &display.theme)
+            }
+            None => code.clone(),
+        };
+        (code, highlighted)
+    };
+
+    let gutter = line_number_gutter(&code, display.line_numbers);
+    let lines = code_body_lines(&code, &highlighted, display, code_room(gutter));
+
+    // The line number gutter is dimmed, which has to go when colors are off
+    paint(&lines.join("\n")) + "\n"
+}
+
+// Function to render only the code lines of a snippet that match a pattern, like grep, under a header
+// naming the snippet. Each match is shown with `context` lines around it, and separate groups of lines
+// are divided by "--". Returns None when no line matches.
+pub fn render_grep(
+    snippet: &Snippet,
+    pattern: &Regex,
+    context: usize,
+    display: &DisplayOptions,
+) -> Option<String> {
+    let code = expand_tabs(&snippet.code, display.tab_width);
+    let plain: Vec<&str> = code.lines().collect();
+    let matches: Vec<usize> = (0..plain.len())
+        .filter(|&index| pattern.is_match(plain[index]))
+        .collect();
+    if matches.is_empty() {
+        return None;
+    }
+
+    // Highlight the whole snippet, so lines in the middle of e.g. a block comment keep their colors
+    let highlighted = match &snippet.language {
+        Some(language) => {
+            highlight_code_snippets(&code, language, snippet.theme.as_deref(), &display.theme)
+        }
+        None => code.clone(),
+    };
+    let highlighted: Vec<&str> = highlighted.lines().collect();
+
+    // Every line to show, a matching line wins over the context of another match
+    let mut shown = vec![None; plain.len()];
+    for &index in &matches {
+        let first = index.saturating_sub(context);
+        let last = (index + context).min(plain.len() - 1);
+        for line in &mut shown[first..=last] {
+            line.get_or_insert(false);
+        }
+    }
+    for &index in &matches {
+        shown[index] = Some(true);
+    }
+
+    // Name the snippet the lines come from, with its tags and language
+    let mut lines = vec![format!(
+        "{} {}{}",
+        style::label(format!("ID {}", snippet.id)),
+        style::value(snippet.tag.join(", ")),
+        snippet
+            .language
+            .as_deref()
+            .map(|language| format!(" ({})", style::language(language)))
+            .unwrap_or_default()
+    )];
+
+    // Numbered lines, ':' after the number of a matching line and '-' after a context line, like grep
+    let number_width = plain.len().to_string().len();
+    let mut previous: Option<usize> = None;
+    for (index, is_match) in shown.iter().enumerate() {
+        let Some(is_match) = is_match else {
+            continue;
+        };
+        if previous.is_some_and(|previous| previous + 1 < index) {
+            lines.push(style::border("--"));
+        }
+        previous = Some(index);
+
+        let line = highlighted.get(index).copied().unwrap_or(plain[index]);
+        let line = if *is_match && color_enabled() {
+            let terms: Vec<String> = pattern
+                .find_iter(plain[index])
+                .map(|found| found.as_str().to_string())
+                .collect();
+            mark_terms(line, &terms)
+        } else {
+            line.to_string()
+        };
+        let separator = if *is_match { ':' } else { '-' };
+        lines.push(format!(
+            "\x1b[2m{:>width$}{}\x1b[0m {}",
+            index + 1,
+            separator,
+            line,
+            width = number_width
+        ));
+    }
+
+    // The line numbers are dimmed, which has to go when colors are off
+    Some(paint(&lines.join("\n")) + "\n" + box_gap())
+}
+
+// Function to draw the thin rule between snippets shown with `view --code-only`
+pub fn code_only_separator() -> String {
+    style::border("─".repeat(box_width_limit())) + "\n"
+}
+
+pub fn print_snippet_summary(snippet: &Snippet, default_theme: &str) {
+    print!("{}", render_summary(snippet, None, default_theme));
+}
+
+// Function to build the summary box of a snippet, with its fuzzy search score when there is one.
+// The code preview is highlighted in the snippet's theme, or the default one.
+pub fn render_summary(snippet: &Snippet, score: Option<i64>, default_theme: &str) -> String {
+    // Format the snippet ID line, marking pinned snippets
+    let id_line = detail_line("ID", snippet.id) + &pin_marker(snippet);
+
+    // Format the fuzzy search score line, only shown for fuzzy searches
+    let score_line = match score {
+        Some(score) => detail_line("Score", score),
+        None => String::new(),
+    };
+
+    // Format the tag line
+    let tag_line = detail_line("Tag", snippet.tags_display());
+
+    // Format the language line, snippets without one are plain text
+    let language_line = language_line(snippet.language.as_deref());
+
+    // Format the creation timestamp line
+    let created_line = detail_line("Created", format_timestamp(&snippet.timestamp));
+
+    // Format the line showing how many times the snippet has been copied
+    let copies_line = detail_line(
+        "Copied",
+        format!(
+            "{} {}",
+            snippet.copy_count,
+            if snippet.copy_count == 1 {
+                "time"
+            } else {
+                "times"
+            }
+        ),
+    );
+
+    // Format the size of the code, so big snippets stand out when scanning
+    let size_line = format!(
+        "{}{}",
+        detail_line("Lines", snippet.code.lines().count()),
+        detail_line("Size", format!("{} bytes", snippet.code.len()))
+    );
+
+    // Format the description line if a description is available
+    let description_line = match &snippet.description {
+        Some(desc) => detail_line("Description", desc),
+        None => String::new(),
+    };
+
+    // Wrap the lines that don't fit in the terminal, skipping the ones without a value
+    let content_limit = box_width_limit().saturating_sub(6).max(20);
+
+    // Preview the first non-blank line of code so the snippet can be recognised, cut to a
+    // single line next to the "Code:" label and highlighted like the full code
+    let code_line = match snippet.code.lines().find(|line| !line.trim().is_empty()) {
+        Some(line) => {
+            let preview = truncate_to_width(
+                &expand_tabs(line.trim(), 4),
+                content_limit.saturating_sub(8),
+            );
+            let preview = match &snippet.language {
+                Some(language) => highlight_code_snippets(
+                    &preview,
+                    language,
+                    snippet.theme.as_deref(),
+                    default_theme,
+                ),
+                None => preview,
+            };
+            format!("  {} {}", style::label("Code:"), preview)
+        }
+        None => String::new(),
+    };
+    let detail_lines: Vec<String> = [
+        id_line,
+        score_line,
+        tag_line,
+        language_line,
+        created_line,
+        copies_line,
+        size_line,
+        description_line,
+        code_line,
+    ]
+    .iter()
+    .filter(|line| !line.is_empty())
+    .flat_map(|line| wrap_detail_line(line, content_limit))
+    .collect();
+
+    // Collect all lines into a vector, stripping ANSI color codes for length calculation
+    let all_lines = detail_lines
+        .iter()
+        .map(|line| strip_ansi_codes(line))
+        .collect::<Vec<_>>();
+
+    // Determine the maximum line length for formatting
+    let max_line_length = all_lines.iter().map(|line| line.width()).max().unwrap_or(0);
+
+    // Set the width for the formatted output, adding extra space for borders
+    let adjusted_width = max_line_length + 4;
+
+    // Start with the top border of the summary box
+    let mut lines = vec![border_line("╔", "═", "╗", adjusted_width)];
+
+    // Add each formatted line within the border
+    for line in &detail_lines {
+        lines.push(format_with_border(line, adjusted_width));
+    }
+
+    // Finish with the bottom border of the summary box
+    lines.push(border_line("╚", "═", "╝", adjusted_width) + box_gap());
+
+    lines.join("\n") + "\n"
+}
+
+// Function to print a border line of the given width using the provided corner and fill characters
+fn print_border(left: &str, fill: &str, right: &str, width: usize) {
+    println!("{}", border_line(left, fill, right, width));
+}
+
+// Function to print sections of lines inside a bordered box, separated by a thin rule
+fn print_box(sections: &[Vec<String>]) {
+    // Determine the widest line, ignoring ANSI color codes
+    let max_line_length = sections
+        .iter()
+        .flatten()
+        .map(|line| strip_ansi_codes(line).width())
+        .max()
+        .unwrap_or(0);
+
+    // Set the width for the formatted output, adding extra space for borders
+    let adjusted_width = max_line_length + 4;
+
+    print_border("╔", "═", "╗", adjusted_width);
+    for (index, section) in sections.iter().enumerate() {
+        if index > 0 {
+            print_border("╟", "─", "╢", adjusted_width);
+        }
+        for line in section {
+            println!("{}", format_with_border(line, adjusted_width));
+        }
+    }
+    print_border("╚", "═", "╝\n", adjusted_width);
+}
+
+// Function to count occurrences of each name, sorted by count descending and then by name
+fn count_by_name(names: impl Iterator<Item = String>) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for name in names {
+        *counts.entry(name).or_insert(0) += 1;
+    }
+
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+// Function to print statistics about the snippets collection
+pub fn print_stats(ctx: &Context, file_path: &str) -> Result<(), CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+
+    // Total lines of code across all snippets
+    let total_lines: usize = snippets.iter().map(|s| s.code.lines().count()).sum();
+
+    // Oldest and newest snippets by their creation time
+    let oldest = snippets
+        .iter()
+        .map(|s| s.timestamp)
+        .min()
+        .map(|time| format_timestamp(&time))
+        .unwrap_or_else(|| "-".to_string());
+    let newest = snippets
+        .iter()
+        .map(|s| s.timestamp)
+        .max()
+        .map(|time| format_timestamp(&time))
+        .unwrap_or_else(|| "-".to_string());
+
+    let totals = vec![
+        format!(
+            "  \x1b[33;1mSnippets:\x1b[0m \x1b[35;1m{}\x1b[0m",
+            snippets.len()
+        ),
+        format!(
+            "  \x1b[33;1mLines of code:\x1b[0m \x1b[35;1m{}\x1b[0m",
+            total_lines
+        ),
+        format!("  \x1b[33;1mOldest:\x1b[0m \x1b[35;1m{}\x1b[0m", oldest),
+        format!("  \x1b[33;1mNewest:\x1b[0m \x1b[35;1m{}\x1b[0m", newest),
+    ];
+
+    // Breakdown of snippets by language, snippets without one count as plain text
+    let mut languages = vec!["  \x1b[33;1mLanguages:\x1b[0m".to_string()];
+    languages.extend(
+        count_by_name(snippets.iter().map(|s| {
+            s.language
+                .clone()
+                .unwrap_or_else(|| "plain text".to_string())
+        }))
+        .into_iter()
+        .map(|(language, count)| {
+            format!(
+                "    \x1b[1;36m»\x1b[0m \x1b[1;33m{}\x1b[0m \x1b[35;1m{}\x1b[0m",
+                language, count
+            )
+        }),
+    );
+
+    // Breakdown of snippets by tag, each tag of a snippet is counted
+    let mut tags = vec!["  \x1b[33;1mTags:\x1b[0m".to_string()];
+    tags.extend(
+        count_by_name(snippets.iter().flat_map(|s| s.tag.clone()))
+            .into_iter()
+            .map(|(tag, count)| {
+                format!(
+                    "    \x1b[1;36m»\x1b[0m \x1b[1;33m{}\x1b[0m \x1b[35;1m{}\x1b[0m",
+                    tag, count
+                )
+            }),
+    );
+
+    print_box(&[totals, languages, tags]);
+
+    Ok(())
+}
+
+// Function to print names with their counts inside a box, or a note when there are none
+fn print_counts(counts: Vec<(String, usize)>, empty_message: &str) {
+    let lines: Vec<String> = counts
+        .into_iter()
+        .map(|(name, count)| {
+            format!(
+                "  \x1b[1;36m»\x1b[0m \x1b[1;33m{}\x1b[0m \x1b[35;1m{}\x1b[0m",
+                name, count
+            )
+        })
+        .collect();
+
+    if lines.is_empty() {
+        println!("{}", paint(&format!("\x1b[1;33m{}\x1b[0m", empty_message)));
+    } else {
+        print_box(&[lines]);
+    }
+}
+
+// Function to list the distinct tags of the collection with the number of snippets carrying each
+pub fn print_tags(ctx: &Context, file_path: &str) -> Result<(), CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+
+    // Each tag of a snippet is counted on its own, the most used tags come first
+    print_counts(
+        count_by_name(snippets.iter().flat_map(|s| s.tag.clone())),
+        "No snippet has a tag yet.",
+    );
+
+    Ok(())
+}
+
+// Function to print the tag hierarchy as an indented tree, with the number of snippets at or below each tag
+pub fn print_tag_tree(ctx: &Context, file_path: &str) -> Result<(), CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+    let tree = TagTree::from_snippets(&snippets);
+
+    if tree.children.is_empty() {
+        println!("{}", paint("\x1b[1;33mNo snippet has a tag yet.\x1b[0m"));
+        return Ok(());
+    }
+
+    // The top level tags start at the left, their children hang off them with tree lines
+    let mut lines = Vec::new();
+    for (name, node) in &tree.children {
+        lines.push(tag_tree_line("  ", name, node.count));
+        tag_tree_lines(node, "  ", &mut lines);
+    }
+    print_box(&[lines]);
+
+    Ok(())
+}
+
+// Function to add the lines for the children of a tag, each prefixed by the tree lines above it
+fn tag_tree_lines(node: &TagTree, prefix: &str, lines: &mut Vec<String>) {
+    let last = node.children.len().saturating_sub(1);
+    for (index, (name, child)) in node.children.iter().enumerate() {
+        let (branch, continuation) = if index == last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(tag_tree_line(
+            &format!("{}{}", prefix, style::border(branch)),
+            name,
+            child.count,
+        ));
+        tag_tree_lines(
+            child,
+            &format!("{}{}", prefix, style::border(continuation)),
+            lines,
+        );
+    }
+}
+
+// Function to format one tag of the tree with its count, in the colors of the tag list
+fn tag_tree_line(prefix: &str, name: &str, count: usize) -> String {
+    format!("{}{} {}", prefix, style::label(name), style::value(count))
+}
+
+// Function to print the lines of code in each language, most lines first, with the number of
+// snippets they come from and the totals in a section of their own
+pub fn print_loc(ctx: &Context, file_path: &str) -> Result<(), CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+    if snippets.is_empty() {
+        println!(
+            "{}",
+            style::label("The collection is empty, there are no lines to count.")
+        );
+        return Ok(());
+    }
+
+    // Lines and snippets per language, snippets without one count as plain text like in the statistics
+    let mut totals: HashMap<String, (usize, usize)> = HashMap::new();
+    for snippet in &snippets {
+        let language = snippet
+            .language
+            .clone()
+            .unwrap_or_else(|| "plain text".to_string());
+        let entry = totals.entry(language).or_insert((0, 0));
+        entry.0 += snippet.code.lines().count();
+        entry.1 += 1;
+    }
+    let mut rows: Vec<(String, usize, usize)> = totals
+        .into_iter()
+        .map(|(language, (lines, count))| (language, lines, count))
+        .collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let total_lines: usize = rows.iter().map(|row| row.1).sum();
+
+    // Line the columns up on the plain text, the colors are added around the padded values
+    let name_width = rows
+        .iter()
+        .map(|row| row.0.width())
+        .chain(["Language".width(), "Total".width()])
+        .max()
+        .unwrap_or(0);
+    let lines_width = total_lines.to_string().len().max("Lines".len());
+    let count_width = snippets.len().to_string().len().max("Snippets".len());
+    let pad = |name: &str| format!("{}{}", name, " ".repeat(name_width - name.width()));
+    let row = |marker: &str, name: &str, lines: usize, count: usize| {
+        format!(
+            "  {} {}  {}  {}",
+            marker,
+            style::label(pad(name)),
+            style::value(format!("{:>lines_width$}", lines)),
+            style::value(format!("{:>count_width$}", count))
+        )
+    };
+
+    let header = vec![format!(
+        "    {}",
+        style::label(format!(
+            "{}  {:>lines_width$}  {:>count_width$}",
+            pad("Language"),
+            "Lines",
+            "Snippets"
+        ))
+    )];
+    let languages = rows
+        .iter()
+        .map(|(language, lines, count)| row(&style::prompt("»"), language, *lines, *count))
+        .collect();
+    let total = vec![row(" ", "Total", total_lines, snippets.len())];
+    print_box(&[header, languages, total]);
+
+    Ok(())
+}
+
+// Function to list the languages used in the collection with the number of snippets in each
+pub fn print_used_languages(ctx: &Context, file_path: &str) -> Result<(), CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+
+    // Snippets without a language count as plain text, like in the statistics
+    print_counts(
+        count_by_name(snippets.iter().map(|s| {
+            s.language
+                .clone()
+                .unwrap_or_else(|| "plain text".to_string())
+        })),
+        "The collection is empty, there are no languages to list.",
+    );
+
+    Ok(())
+}
+
+// Function to capture a code snippet from standard input
+pub fn capture_snippet(ctx: &Context) -> Result<String, CodevaultError> {
+    let mut buffer = String::new(); // Create a buffer to store the input
+
+    // Only guide the user when typing into a terminal, piped code is read silently.
+    // The guidance goes to stderr, so stdout only carries the outcome.
+    if io::stdin().is_terminal() && !ctx.quiet {
+        eprintln!("{}", paint("\n\x1b[38;5;201;1mCapture snippet:\x1b[0m\n"));
+        eprintln!("{}", paint("\x1b[1;36m Enter your code snippet (press \x1b[33m'Return'\x1b[1;36m, then \x1b[33m'Ctrl+D'\x1b[1;36m to finish):\x1b[0m"));
+        eprintln!("{}", paint("\x1b[1;31m Note:\x1b[0m \x1b[1;33m'Arrow Keys'\x1b[0m \x1b[1;36mare not captured, use \x1b[1;33m'Backspace'\x1b[0m \x1b[1;36mto erase inputs\x1b[0m"));
+    }
+
+    // Read the entire input into the buffer, e.g. input that is not UTF-8 is reported as an error
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .map_err(CodevaultError::io("reading snippet from input"))?;
+    Ok(buffer)
+}
+
+// Function to check a language typed by the user is known to syntect, suggesting the closest
+// supported language when it is not
+pub fn validate_language(
+    language: &str,
+    supported_languages: &[&str],
+) -> Result<(), CodevaultError> {
+    // Accept any language syntect knows by name, alias or extension (e.g. "rs" or "py")
+    let language = language.trim();
+    if resolve_language(language).is_some() {
+        return Ok(());
+    }
+
+    // Suggest the closest supported language when the input looks like a typo
+    let lowered = language.to_lowercase();
+    let suggestion = supported_languages
+        .iter()
+        .map(|name| (edit_distance(&lowered, &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= 2.max(lowered.chars().count() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_string());
+
+    Err(CodevaultError::UnsupportedLanguage {
+        language: language.to_string(),
+        suggestion,
+    })
+}
+
+// Function to store languages under their syntax name, so "cpp" and "C++" are saved the same way
+pub fn canonical_language(language: &str) -> String {
+    resolve_language(language)
+        .map(|syntax| syntax.name.clone())
+        .unwrap_or_else(|| language.trim().to_string())
+}
+
+// Function to detect the language of a code snippet from its first line (shebangs, modelines, etc.)
+pub fn detect_language(code: &str, ps: &SyntaxSet) -> Option<String> {
+    ps.find_syntax_by_first_line(code)
+        .filter(|syntax| syntax.name != ps.find_syntax_plain_text().name)
+        .map(|syntax| syntax.name.clone())
+}
+
+// Function to capture every new text copied to the clipboard as a snippet, until interrupted with Ctrl+C
+pub fn watch_clipboard(
+    ctx: &Context,
+    file_path: &str,
+    tag: &Option<String>,
+    description: &Option<String>,
+    language: &Option<String>,
+    allow_duplicate: bool,
+) -> Result<(), CodevaultError> {
+    // Tags are asked for each new text, which needs a terminal unless they are given up front
+    let interactive = io::stdin().is_terminal() && !ctx.quiet;
+    if tag.is_none() && !interactive {
+        return Err(CodevaultError::MissingArgument("--tag"));
+    }
+
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|err| CodevaultError::Clipboard(err.to_string()))?;
+    let ps = syntax_set();
+
+    // Only text copied from now on is captured, anything that isn't text reads as empty
+    let mut last = clipboard.get_text().unwrap_or_default();
+
+    // Everything but the captured IDs goes to stderr, next to the questions
+    ctx.note(&format!("\n{}\n", style::header("Watching the clipboard:")));
+    ctx.note(&style::prompt(
+        " Copy code to capture it, press 'Ctrl+C' to stop",
+    ));
+
+    loop {
+        thread::sleep(CLIPBOARD_POLL);
+        let text = clipboard.get_text().unwrap_or_default();
+        if text == last {
+            continue;
+        }
+
+        // Wait for the clipboard to settle, so a burst of copies is captured once
+        thread::sleep(CLIPBOARD_POLL);
+        if clipboard.get_text().unwrap_or_default() != text {
+            continue;
+        }
+        last = text.clone();
+
+        // Skip empty or whitespace-only content
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        // Show the first line of the copied code so the user knows what is being captured
+        let first_line = text
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("");
+        ctx.note(&format!(
+            "\n{} {}",
+            style::label("Copied:"),
+            first_line.trim()
+        ));
+
+        // Ask for the tags unless they were given, leaving them blank skips this text
+        let tags = match tag {
+            Some(tag) => tag.clone(),
+            None => prompt(&format!(
+                "{}{}{}",
+                style::prompt("Enter tags, comma-separated ("),
+                style::label("leave blank to skip"),
+                style::prompt("): ")
+            )),
+        };
+        if tags.is_empty() {
+            ctx.note(&style::prompt("Skipped."));
+            continue;
+        }
+
+        // Use the given language, otherwise ask for one and detect it from the code when left blank
+        let chosen = match language {
+            Some(language) => Some(language.clone()),
+            None if interactive => Some(prompt(&format!(
+                "{}{}{}",
+                style::prompt("Enter the language ("),
+                style::label("leave blank to detect it from the code"),
+                style::prompt("): ")
+            )))
+            .filter(|language| !language.is_empty()),
+            None => None,
+        };
+        let language = match chosen {
+            Some(language) if resolve_language(&language).is_some() => {
+                Some(canonical_language(&language))
+            }
+            Some(language) => {
+                eprintln!(
+                    "{}",
+                    style::error(format!(
+                        "Unsupported language '{}', detecting it from the code instead.",
+                        language
+                    ))
+                );
+                detect_language(&text, ps)
+            }
+            None => detect_language(&text, ps),
+        };
+
+        let snippet = Snippet {
+            tag: parse_tags(&tags),
+            description: description.clone().filter(|desc| !desc.is_empty()),
+            code: text,
+            timestamp: Local::now(),
+            language,
+            id: generate_unique_id(ctx, file_path)?,
+            modified: None,
+            pinned: false,
+            copy_count: 0,
+            source: Some("clipboard".to_string()),
+            history: Vec::new(),
+            theme: None,
+        };
+        let id = snippet.id;
+
+        if save_snippet(ctx, snippet, file_path, allow_duplicate, false)? {
+            ctx.announce(&format!("\x1b[1;32mSnippet captured as ID {}.\x1b[0m", id));
+        }
+    }
+}
+
+// Function to detect the language of a file from its extension, or its whole name for files like "Makefile"
+pub fn detect_language_from_path(path: &Path, ps: &SyntaxSet) -> Option<String> {
+    [path.extension(), path.file_name()]
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part.to_str())
+        .find_map(|part| ps.find_syntax_by_extension(part))
+        .filter(|syntax| syntax.name != ps.find_syntax_plain_text().name)
+        .map(|syntax| syntax.name.clone())
+}
+
+// Function to ask the user a yes/no question, adding the (y/N) or (Y/n) hint for the default.
+// Enter takes the default in a terminal. Piped answers work too, e.g. `printf 'y\n' | codevault
+// delete -i 3`, but when piped input runs out or leaves the answer empty, e.g. `< /dev/null`,
+// nobody was there to confirm so it fails instead of guessing. --yes answers yes and --quiet the
+// default without asking.
+pub fn prompt_yes_no(ctx: &Context, question: &str, default: bool) -> Result<bool, CodevaultError> {
+    if ctx.assume_yes {
+        return Ok(true);
+    }
+    if ctx.quiet {
+        return Ok(default);
+    }
+    let hint = if default { "Y/n" } else { "y/N" };
+    loop {
+        eprint!(
+            "{} {}{}{}",
+            question,
+            style::prompt("("),
+            style::label(hint),
+            style::prompt("): ")
+        );
+        io::stderr().flush().unwrap();
+
+        // Nothing left to read, end the prompt's line so the next message starts on its own
+        let Some(answer) = read_answer() else {
+            eprintln!();
+            return take_default(default);
+        };
+        match answer.to_lowercase().as_str() {
+            "" => return take_default(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => eprintln!("{}", style::error("Please answer y or n.")),
+        }
+    }
+}
+
+// The default answer of a confirmation, unless stdin is piped and nobody could have confirmed it
+fn take_default(default: bool) -> Result<bool, CodevaultError> {
+    if io::stdin().is_terminal() {
+        Ok(default)
+    } else {
+        Err(CodevaultError::ConfirmationRequired)
+    }
+}
+
+// Function to ask the user for a line of text, returned without surrounding whitespace.
+// Questions go to stderr like every prompt, so they never end up in redirected output.
+// At the end of piped input the answer is empty.
+pub fn prompt(question: &str) -> String {
+    eprint!("{}", question);
+    io::stderr().flush().unwrap();
+
+    // Nothing left to read, end the prompt's line so the next message starts on its own
+    read_answer().unwrap_or_else(|| {
+        eprintln!();
+        String::new()
+    })
+}
+
+// Function to read one answer from stdin without surrounding whitespace, None when there is
+// nothing left to read
+fn read_answer() -> Option<String> {
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input.trim().to_string()),
+    }
+}
+
+// Function to write code in the user's editor and read it back once the editor exits
+pub fn open_in_editor(initial_code: &str) -> Result<String, CodevaultError> {
+    // Write the initial code to a new temporary file the editor can open, it gets a random name
+    // and only the user can read it, so nothing else can be waiting at that path
+    let mut temp_file = tempfile::Builder::new()
+        .prefix("codevault-")
+        .suffix(".txt")
+        .tempfile()
+        .map_err(CodevaultError::io("creating temporary file"))?;
+    temp_file
+        .write_all(initial_code.as_bytes())
+        .and_then(|_| temp_file.flush())
+        .map_err(CodevaultError::io("writing temporary file"))?;
+
+    // Read the edited code back, the temporary file is removed once it goes out of scope
+    launch_editor(temp_file.path(), ", snippet was not saved")?;
+    std::fs::read_to_string(temp_file.path()).map_err(CodevaultError::io("reading temporary file"))
+}
+
+// Function to show a snippet in the user's editor, from a read-only temporary file named after
+// its language so the editor highlights it. Nothing is read back, edits are made with `edit`.
+pub fn view_in_editor(snippet: &Snippet) -> Result<(), CodevaultError> {
+    // A new file with a random name, created like the one open_in_editor uses
+    let mut temp_file = tempfile::Builder::new()
+        .prefix(&format!("codevault-{}-", snippet.id))
+        .suffix(&format!(
+            ".{}",
+            language_extension(snippet.language.as_deref())
+        ))
+        .tempfile()
+        .map_err(CodevaultError::io("creating temporary file"))?;
+    temp_file
+        .write_all(snippet.code.as_bytes())
+        .and_then(|_| temp_file.flush())
+        .map_err(CodevaultError::io("writing temporary file"))?;
+    let temp_path = temp_file.path().to_path_buf();
+
+    // Read-only, so the editor warns before anyone expects a change to be saved
+    let set_readonly = |readonly: bool| {
+        let mut permissions = std::fs::metadata(&temp_path)?.permissions();
+        permissions.set_readonly(readonly);
+        std::fs::set_permissions(&temp_path, permissions)
+    };
+    let result = set_readonly(true)
+        .map_err(CodevaultError::io("protecting temporary file"))
+        .and_then(|_| launch_editor(&temp_path, ""));
+
+    // The temporary file is removed when it is dropped, some systems refuse to delete read-only files
+    let _ = set_readonly(false);
+
+    result
+}
+
+// Function to run the editor from $VISUAL or $EDITOR on a file and wait for it to exit,
+// the note is added to the error when the editor fails
+fn launch_editor(path: &Path, failure_note: &str) -> Result<(), CodevaultError> {
+    // Prefer $VISUAL, then $EDITOR, and fall back to vi
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // The editor variable may carry arguments, e.g. "code --wait"
+    let mut editor_parts = editor.split_whitespace();
+    let program = editor_parts.next().ok_or_else(|| {
+        CodevaultError::Editor("no editor configured in $VISUAL or $EDITOR".to_string())
+    })?;
+
+    // Launch the editor and wait for it to exit
+    match Command::new(program).args(editor_parts).arg(path).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(CodevaultError::Editor(format!(
+            "editor '{}' exited with {}{}",
+            program, status, failure_note
+        ))),
+        Err(err) => Err(CodevaultError::io(format!(
+            "launching editor '{}'",
+            program
+        ))(err)),
+    }
+}
+
+// Function to save a snippet to a JSON file. With append_only the snippet is added to the end of
+// the file without building the collection, only the code of the records is read for the
+// duplicate check.
+pub fn save_snippet(
+    ctx: &Context,
+    snippet: Snippet,
+    file_path: &str,
+    allow_duplicate: bool,
+    append_only: bool,
+) -> Result<bool, CodevaultError> {
+    // Look for identical code in the records as they are, without building the snippets
+    let mut checked = allow_duplicate;
+    if append_only && !allow_duplicate {
+        if let Some(records) = read_append_records(ctx, file_path) {
+            let code = snippet.code.trim();
+            if let Some(existing) = records.iter().find(|record| record.code.trim() == code) {
+                if !confirm_duplicate(ctx, existing.id)? {
+                    return Ok(false);
+                }
+            }
+            checked = true;
+        }
+    }
+
+    // Add the snippet to the end of the file as it is, without reading the collection back in
+    if append_only && checked && append_snippet_record(ctx, &snippet, file_path)? {
+        return Ok(true);
+    }
+
+    // Load the existing snippets, if the file does not exist start with an empty vector
+    let mut snippets: Vec<Snippet> = if Path::new(file_path).exists() {
+        load_snippets(ctx, file_path)?
+    } else {
+        Vec::new()
+    };
+
+    // Warn when the same code is already in the collection and let the user decide
+    if !checked {
+        let hash = snippet.code_hash();
+        if let Some(existing) = snippets.iter().find(|s| s.code_hash() == hash) {
+            if !confirm_duplicate(ctx, existing.id)? {
+                return Ok(false);
+            }
+        }
+    }
+
+    snippets.push(snippet); // Add the new snippet to the vector
+
+    // Write the whole collection back to the file
+    save_snippets_for_edit(ctx, snippets, file_path)?;
+
+    Ok(true)
+}
+
+// Function to ask whether to save a snippet whose code is already in the collection
+fn confirm_duplicate(ctx: &Context, existing_id: u32) -> Result<bool, CodevaultError> {
+    let question = format!(
+        "\n{} {}",
+        style::label(format!("This looks identical to snippet #{}.", existing_id)),
+        style::prompt("Save it anyway?")
+    );
+    prompt_yes_no(ctx, &question, false)
+}
+
+// Function to add a snippet to the end of the JSON array in the data file, writing the record
+// where the closing bracket was instead of serializing the collection again. The result goes
+// through a temporary file like every save, so an interrupted append leaves the file as it was.
+// Returns false when the file can't be appended to this way, i.e. it is missing, encrypted or not
+// laid out as a JSON array.
+fn append_snippet_record(
+    ctx: &Context,
+    snippet: &Snippet,
+    file_path: &str,
+) -> Result<bool, CodevaultError> {
+    if ctx.encryption_enabled() {
+        return Ok(false);
+    }
+    let mut content = match std::fs::read(file_path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(CodevaultError::io(format!("reading file '{}'", file_path))(
+                err,
+            ))
+        }
+    };
+
+    // An encrypted vault starts with its header, its records can't be appended as text
+    if is_encrypted(&content) {
+        return Ok(false);
+    }
+    if content.trim_ascii().is_empty() {
+        content = b"[]".to_vec();
+    }
+
+    // The array is closed by the last non-blank character, whatever is before it stays untouched
+    let Some(body) = content.trim_ascii_end().strip_suffix(b"]") else {
+        return Ok(false);
+    };
+    let body = body.trim_ascii_end();
+    let separator: &[u8] = match body.last() {
+        Some(b'[') => b"\n",
+        Some(b'}') => b",\n",
+        _ => return Ok(false),
+    };
+    let end = body.len();
+
+    // Indent the record like the ones `serde_json::to_vec_pretty` writes inside the array
+    let record = serde_json::to_string_pretty(snippet).map_err(CodevaultError::Serialize)?;
+    let record = record
+        .lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Put the record where the closing bracket was, then close the array again
+    content.truncate(end);
+    content.extend_from_slice(separator);
+    content.extend_from_slice(record.as_bytes());
+    content.extend_from_slice(b"\n]");
+    write_file_atomically(&content, file_path)?;
+    Ok(true)
+}
+
+// Function to write JSON to a temporary file next to the data file and rename it into place,
+// so an interrupted save never leaves a truncated collection behind
+fn write_json_atomically<T: Serialize + ?Sized>(
+    ctx: &Context,
+    value: &T,
+    file_path: &str,
+) -> Result<(), CodevaultError> {
+    // Serialize the snippets, encrypting them when the vault is encrypted
+    let mut content = serde_json::to_vec_pretty(value).map_err(CodevaultError::Serialize)?;
+    if ctx.encryption_enabled() {
+        content = ctx.encrypt_vault(&content)?;
+    }
+    write_file_atomically(&content, file_path)
+}
+
+// Function to write the content to a temporary file next to the data file and rename it into place
+fn write_file_atomically(content: &[u8], file_path: &str) -> Result<(), CodevaultError> {
+    let path = Path::new(file_path);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "codevault.json".to_string());
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    // Write the content into the temporary file and flush it to disk
+    let write_result = File::create(&temp_path)
+        .map_err(CodevaultError::io(format!(
+            "creating file '{}'",
+            temp_path.display()
+        )))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            writer
+                .write_all(content)
+                .map_err(CodevaultError::io(format!(
+                    "writing file '{}'",
+                    temp_path.display()
+                )))?;
+            let file = writer.into_inner().map_err(|err| {
+                CodevaultError::io(format!("writing file '{}'", temp_path.display()))(
+                    err.into_error(),
+                )
+            })?;
+            file.sync_all().map_err(CodevaultError::io(format!(
+                "writing file '{}'",
+                temp_path.display()
+            )))
+        });
+
+    // Replace the original file only once the new content is complete
+    let result = write_result.and_then(|_| {
+        std::fs::rename(&temp_path, path).map_err(CodevaultError::io(format!(
+            "replacing file '{}'",
+            file_path
+        )))
+    });
+
+    // Don't leave the temporary file behind if anything failed
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+// Function to find the config file, '~/.config/codevault/config.toml' on Linux
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("codevault").join("config.toml"))
+}
+
+// Function to read the config file, a missing file gives the defaults
+pub fn load_config(path: &Path) -> Result<Config, CodevaultError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => {
+            return Err(CodevaultError::io(format!("reading '{}'", path.display()))(
+                err,
+            ));
+        }
+    };
+    let config_error = |message: String| CodevaultError::Config {
+        path: path.display().to_string(),
+        message,
+    };
+    let config: Config = toml::from_str(&content).map_err(|err| config_error(err.to_string()))?;
+
+    // A misspelled theme would otherwise only fail once something gets highlighted
+    validate_theme(&config.theme).map_err(|err| config_error(err.to_string()))?;
+
+    Ok(config)
+}
+
+// Function to check that a theme is one of syntect's default themes, listing them when it isn't
+pub fn validate_theme(theme: &str) -> Result<(), CodevaultError> {
+    let ts = theme_set();
+    if ts.themes.contains_key(theme) {
+        return Ok(());
+    }
+    Err(CodevaultError::UnknownTheme {
+        theme: theme.to_string(),
+        available: ts.themes.keys().cloned().collect::<Vec<_>>().join(", "),
+    })
+}
+
+// Function to find a capture template in the config file
+pub fn find_template<'a>(ctx: &'a Context, name: &str) -> Result<&'a Template, CodevaultError> {
+    ctx.config
+        .templates
+        .get(name)
+        .ok_or_else(|| CodevaultError::TemplateNotFound(name.to_string()))
+}
+
+// Function to read the config file for editing, keeping its comments and layout
+fn read_config_document(path: &Path) -> Result<toml_edit::DocumentMut, CodevaultError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(CodevaultError::io(format!("reading '{}'", path.display()))(
+                err,
+            ));
+        }
+    };
+    content
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|err| CodevaultError::Config {
+            path: path.display().to_string(),
+            message: err.to_string(),
+        })
+}
+
+// Function to write the edited config file back, creating its directory the first time
+fn write_config_document(
+    path: &Path,
+    document: &toml_edit::DocumentMut,
+) -> Result<(), CodevaultError> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(CodevaultError::io(format!(
+            "creating directory '{}'",
+            dir.display()
+        )))?;
+    }
+    std::fs::write(path, document.to_string())
+        .map_err(CodevaultError::io(format!("writing '{}'", path.display())))
+}
+
+// Function to save a capture template as a `[templates.<name>]` table of the config file,
+// returning whether it replaced one with the same name. The rest of the file is left as it is.
+pub fn add_template(
+    config_path: &Path,
+    name: &str,
+    template: &Template,
+) -> Result<bool, CodevaultError> {
+    let mut document = read_config_document(config_path)?;
+    let templates = document
+        .entry("templates")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| CodevaultError::Config {
+            path: config_path.display().to_string(),
+            message: "'templates' is not a table".to_string(),
+        })?;
+    // Only the `[templates.<name>]` headers are written, not an empty `[templates]` one
+    templates.set_implicit(true);
+
+    let mut table = toml_edit::Table::new();
+    for (key, value) in [
+        ("tag", &template.tag),
+        ("description", &template.description),
+        ("language", &template.language),
+    ] {
+        if let Some(value) = value {
+            table.insert(key, toml_edit::value(value.as_str()));
+        }
+    }
+    let replaced = templates
+        .insert(name, toml_edit::Item::Table(table))
+        .is_some();
+
+    write_config_document(config_path, &document)?;
+    Ok(replaced)
+}
+
+// Function to delete a capture template from the config file
+pub fn remove_template(config_path: &Path, name: &str) -> Result<(), CodevaultError> {
+    let mut document = read_config_document(config_path)?;
+    let removed = document
+        .get_mut("templates")
+        .and_then(|templates| templates.as_table_like_mut())
+        .and_then(|templates| templates.remove(name));
+    if removed.is_none() {
+        return Err(CodevaultError::TemplateNotFound(name.to_string()));
+    }
+    write_config_document(config_path, &document)
+}
+
+// Function to list the capture templates of the config file with the values they fill in
+pub fn print_templates(ctx: &Context) {
+    let lines: Vec<String> = ctx
+        .config
+        .templates
+        .iter()
+        .map(|(name, template)| {
+            let values = [
+                ("tag", &template.tag),
+                ("description", &template.description),
+                ("language", &template.language),
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| {
+                value.as_ref().map(|value| {
+                    format!(
+                        "{} {}",
+                        style::prompt(format!("{}:", key)),
+                        style::value(value)
+                    )
+                })
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+            format!(
+                "  {} {}  {}",
+                style::prompt("»"),
+                style::label(name),
+                values
+            )
+        })
+        .collect();
+
+    if lines.is_empty() {
+        println!(
+            "{}",
+            style::label("No templates yet, add one with 'codevault template add'.")
+        );
+    } else {
+        print_box(&[lines]);
+    }
+}
+
+// Function to get the file marking that the large collection warning was given, next to the data file
+fn large_vault_marker_path(file_path: &str) -> PathBuf {
+    Path::new(file_path).with_extension("large-vault")
+}
+
+// Function to load snippets from a JSON file, warning about problems a command should know about
+pub fn load_snippets(ctx: &Context, file_path: &str) -> Result<Vec<Snippet>, CodevaultError> {
+    let snippets = read_snippets(ctx, file_path)?;
+
+    // Every command reads and writes the whole file, which gets slow for very large collections.
+    // Said only once per collection, a marker file next to it remembers the warning was given.
+    let marker = large_vault_marker_path(file_path);
+    if snippets.len() >= LARGE_VAULT && !marker.exists() {
+        let _ = std::fs::write(&marker, "");
+        eprintln!(
+            "{}",
+            paint(&format!(
+                "\n\x1b[1;33mwarning:\x1b[0m the collection has {}+ snippets, consider splitting it into several files with \x1b[1;33m--data\x1b[0m",
+                LARGE_VAULT
+            ))
+        );
+    }
+
+    // Duplicate IDs make every lookup by ID pick the first match. Reading never changes the file,
+    // so only point at `repair`; commands that save the collection renumber them on the way.
+    let duplicates = duplicate_ids(&snippets);
+    if !duplicates.is_empty() && !ctx.duplicates_reported.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "{}",
+            paint(&format!(
+                "\n\x1b[1;33mwarning:\x1b[0m the snippets file '\x1b[1;33m{}\x1b[0m' has duplicate IDs: {}",
+                file_path,
+                duplicates
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        );
+        eprintln!(
+            "{}",
+            paint("\x1b[1;36mRun '\x1b[1;33mcodevault repair\x1b[1;36m' to renumber them.\x1b[0m")
+        );
+    }
+
+    Ok(snippets)
+}
+
+// Function to read the snippets of a JSON file as they are stored, without any integrity check
+fn read_snippets(ctx: &Context, file_path: &str) -> Result<Vec<Snippet>, CodevaultError> {
+    // Open the file and read its content into a vector of snippets
+    let content = read_vault_file(ctx, file_path)?;
+    match serde_json::from_slice(&content) {
+        Ok(snippets) => Ok(snippets),
+        Err(err) => recover_malformed_file(ctx, file_path, err),
+    }
+}
+
+// Function to find the IDs used by more than one snippet, in ascending order
+fn duplicate_ids(snippets: &[Snippet]) -> Vec<u32> {
+    let mut ids: Vec<u32> = snippets.iter().map(|s| s.id).collect();
+    ids.sort_unstable();
+    let mut duplicates: Vec<u32> = ids
+        .windows(2)
+        .filter(|pair| pair[0] == pair[1])
+        .map(|pair| pair[0])
+        .collect();
+    duplicates.dedup();
+    duplicates
+}
+
+// Function to give every snippet after the first one with the same ID a new ID above all
+// existing ones, returning each renumbered ID with the one it got
+fn renumber_duplicate_ids(snippets: &mut [Snippet]) -> Vec<(u32, u32)> {
+    let mut next_id = snippets.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+    let mut seen = HashSet::new();
+    let mut renumbered = Vec::new();
+    for snippet in snippets.iter_mut() {
+        if !seen.insert(snippet.id) {
+            renumbered.push((snippet.id, next_id));
+            snippet.id = next_id;
+            next_id += 1;
+        }
+    }
+    renumbered
+}
+
+// Function to renumber duplicate IDs in the snippets file on demand, returning the renumbered IDs
+pub fn repair_snippets(ctx: &Context, file_path: &str) -> Result<Vec<(u32, u32)>, CodevaultError> {
+    let mut snippets = read_snippets(ctx, file_path)?;
+    let renumbered = renumber_duplicate_ids(&mut snippets);
+
+    // Leave the file untouched when there is nothing to fix
+    if !renumbered.is_empty() {
+        write_json_atomically(ctx, &snippets, file_path)?;
+    }
+
+    Ok(renumbered)
+}
+
+// Function to read a vault file, decrypting it when it is encrypted
+fn read_vault_file(ctx: &Context, file_path: &str) -> Result<Vec<u8>, CodevaultError> {
+    let content = std::fs::read(file_path)
+        .map_err(CodevaultError::io(format!("opening file '{}'", file_path)))?;
+    if !is_encrypted(&content) {
+        return Ok(content);
+    }
+
+    // Keep an encrypted vault encrypted when it is written back
+    ctx.enable_encryption();
+    ctx.decrypt_vault(&content)
+}
+
+// Function to offer backing up a malformed snippets file and starting with an empty collection
+fn recover_malformed_file(
+    ctx: &Context,
+    file_path: &str,
+    err: serde_json::Error,
+) -> Result<Vec<Snippet>, CodevaultError> {
+    // Without a terminal to ask, report the malformed file itself, piped input may be the code to capture
+    let malformed = |err| CodevaultError::Deserialize {
+        path: file_path.to_string(),
+        source: err,
+    };
+    if !io::stdin().is_terminal() {
+        return Err(malformed(err));
+    }
+    eprintln!(
+        "{}",
+        paint(&format!(
+            "\n\x1b[31merror:\x1b[0m the snippets file '\x1b[1;33m{}\x1b[0m' is malformed: {}",
+            file_path, err
+        ))
+    );
+
+    // Keep the malformed file untouched unless the user agrees to back it up
+    let backup_path = format!("{}.bak", file_path);
+    if !prompt_yes_no(
+        ctx,
+        &style::prompt(format!(
+            "Back it up to '{}' and start with an empty collection?",
+            backup_path
+        )),
+        false,
+    )? {
+        return Err(malformed(err));
+    }
+
+    // Move the malformed file aside and replace it with an empty collection
+    std::fs::rename(file_path, &backup_path)
+        .map_err(CodevaultError::io(format!("backing up '{}'", file_path)))?;
+    std::fs::write(file_path, "[]")
+        .map_err(CodevaultError::io(format!("creating '{}'", file_path)))?;
+
+    eprintln!(
+        "{}",
+        paint(&format!(
+            "\x1b[1;32mThe malformed file was backed up to '{}'.\x1b[0m\n",
+            backup_path
+        ))
+    );
+    Ok(Vec::new())
+}
+
+// Function to view snippets based on various filters like ID, tag, language, and keyword
+pub fn view_snippets(
+    ctx: &Context,
+    file_path: &str,
+    filter: &ViewFilter,
+) -> Result<Vec<Snippet>, CodevaultError> {
+    let ViewFilter {
+        id,
+        tag,
+        language,
+        keyword,
+        fuzzy,
+        pinned,
+        sort,
+        reverse,
+        exact,
+    } = filter;
+
+    // Load all snippets from the specified file
+    let snippets = load_snippets(ctx, file_path)?;
+    let matcher = SkimMatcherV2::default();
+
+    // Filter snippets based on provided criteria
+    let mut filtered_snippets = snippets
+        .into_iter()
+        .filter(|snippet| {
+            // Check if the snippet's tag matches any of the provided tags
+            let tag_match = tag
+                .as_ref()
+                .is_none_or(|tag| snippet.matches_tags(tag, *exact));
+
+            // Check if the snippet's language matches any of the provided languages
+            let language_match = language
+                .as_ref()
+                .is_none_or(|language| snippet.matches_languages(language, *exact));
+
+            // Check if the snippet contains any of the provided keywords in its tag, description, or code
+            let keyword_match = if let Some(keyword) = keyword {
+                let keywords: Vec<&str> = keyword.split(',').map(|s| s.trim()).collect();
+                keywords.iter().any(|k| {
+                    snippet.has_tag(k)
+                        || snippet
+                            .description
+                            .as_ref()
+                            .map(|desc| desc.to_lowercase().contains(&k.to_lowercase()))
+                            .unwrap_or(false)
+                        || snippet.code.to_lowercase().contains(&k.to_lowercase())
+                })
+            } else {
+                true
+            };
+
+            // Check if the snippet is pinned when only pinned snippets are requested
+            let pinned_match = !pinned || snippet.pinned;
+
+            // Check if the snippet fuzzily matches the query
+            let fuzzy_match = fuzzy
+                .as_ref()
+                .is_none_or(|query| snippet.fuzzy_score(&matcher, query).is_some());
+
+            tag_match && language_match && keyword_match && pinned_match && fuzzy_match
+        })
+        .collect::<Vec<_>>(); // Collect the filtered snippets into a vector
+
+    // Order by ID first so snippets with equal sort keys keep a stable order
+    filtered_snippets.sort_by_key(|s| s.id);
+
+    // Order the snippets by the requested field, snippets never edited sort as oldest
+    match sort {
+        SortKey::Id => {}
+        SortKey::Created => filtered_snippets.sort_by_key(|s| s.timestamp),
+        SortKey::Modified => filtered_snippets.sort_by_key(|s| s.modified),
+        SortKey::Tag => filtered_snippets.sort_by_key(|s| s.tags_display().to_lowercase()),
+        SortKey::Language => {
+            filtered_snippets.sort_by_key(|s| s.language.as_ref().map(|l| l.to_lowercase()))
+        }
+        // Most copied snippets come first
+        SortKey::Copies => filtered_snippets.sort_by_key(|s| std::cmp::Reverse(s.copy_count)),
+    }
+
+    // Reverse the order if requested
+    if *reverse {
+        filtered_snippets.reverse();
+    }
+
+    // The default ordering lists pinned snippets first
+    if *sort == SortKey::Id {
+        filtered_snippets.sort_by_key(|s| !s.pinned);
+    }
+
+    // A fuzzy search lists the best matches first
+    if let Some(query) = fuzzy {
+        filtered_snippets.sort_by_cached_key(|s| std::cmp::Reverse(s.fuzzy_score(&matcher, query)));
+    }
+
+    // If an ID is specified, filter to include only the snippet with that ID
+    if let Some(id) = id {
+        if let Some(index) = filtered_snippets.iter().position(|s| s.id == *id) {
+            filtered_snippets = vec![filtered_snippets[index].clone()];
+        } else {
+            return Err(CodevaultError::NotFound(*id));
+        }
+    }
+
+    Ok(filtered_snippets) // Return the filtered snippets
+}
+
+pub fn recent_snippets(
+    ctx: &Context,
+    file_path: &str,
+    count: usize,
+) -> Result<Vec<Snippet>, CodevaultError> {
+    // Load all snippets from the specified file
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    // Order by creation time, newest first
+    snippets.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
+    snippets.truncate(count);
+
+    Ok(snippets)
+}
+
+pub fn edit_snippet(
+    ctx: &Context,
+    file_path: &str,
+    id: &Option<u32>,
+    tag: &Option<String>,
+    fields: &EditFields,
+    use_editor: bool,
+    dry_run: bool,
+) -> Result<EditOutcome, CodevaultError> {
+    // Load existing snippets from the file
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    // Determine which snippet to edit based on ID or tag
+    let mut snippet_to_edit = match (id, tag) {
+        // If an ID is provided, find the snippet with that ID
+        (Some(snippet_id), _) => {
+            if let Some(index) = snippets.iter().position(|s| s.id == *snippet_id) {
+                snippets.remove(index)
+            } else {
+                return Err(CodevaultError::NotFound(*snippet_id));
+            }
+        }
+        // If no ID is provided but a tag is, find snippets with that tag
+        (None, Some(snippet_tag)) => {
+            let matching_snippets: Vec<&Snippet> =
+                snippets.iter().filter(|s| s.has_tag(snippet_tag)).collect();
+
+            // Handle cases where no snippets or multiple snippets match the tag
+            if matching_snippets.is_empty() {
+                return Err(CodevaultError::NoMatch(snippet_tag.clone()));
+            } else if matching_snippets.len() > 1 && ctx.quiet {
+                // Choosing from the list needs a prompt, so the ID has to be given instead
+                return Err(CodevaultError::MissingArgument("--id"));
+            } else if matching_snippets.len() > 1 {
+                eprintln!(
+                    "{}",
+                    spaced(&format!("\n{}\n", style::header("Edit snippet:")))
+                );
+                eprintln!(
+                    "{}{}{}\n",
+                    style::prompt("Multiple matching tags found, choose an "),
+                    style::label("ID"),
+                    style::prompt(" to edit from list:")
+                );
+                for snippet in matching_snippets.iter() {
+                    eprintln!(
+                        "{} {}",
+                        style::prompt("  »"),
+                        style::label(format!("ID {}", snippet.id))
+                    );
+                }
+
+                // Prompt the user to select an ID to edit
+                loop {
+                    eprint!(
+                        "\n{}{}{}",
+                        style::prompt("Type the "),
+                        style::label("ID"),
+                        style::prompt(" of the snippet you want to modify: ")
+                    );
+                    io::stderr().flush().unwrap();
+                    // Stop asking once there is nothing left to read, instead of asking forever
+                    let Some(input) = read_answer() else {
+                        eprintln!();
+                        return Ok(EditOutcome::Cancelled);
+                    };
+                    let input_trimmed = input.as_str();
+
+                    if let Ok(chosen_id) = input_trimmed.parse::<u32>() {
+                        if matching_snippets.iter().any(|s| s.id == chosen_id) {
+                            if let Some(index) = snippets.iter().position(|s| s.id == chosen_id) {
+                                break snippets.remove(index);
+                            }
+                        } else {
+                            eprintln!(
+                                "{}{}{}",
+                                style::error("ID '"),
+                                style::label(chosen_id),
+                                style::error("' is not in the list. Please choose a valid ID.")
+                            );
+                        }
+                    } else {
+                        eprintln!(
+                            "{}",
+                            style::error(
+                                "Invalid input. Please enter a valid numeric ID from the list."
+                            )
+                        );
+                    }
+                }
+            } else {
+                // Take the single match out of the collection, so saving it doesn't add a second copy
+                let id = matching_snippets[0].id;
+                let index = snippets
+                    .iter()
+                    .position(|s| s.id == id)
+                    .ok_or(CodevaultError::NotFound(id))?;
+                snippets.remove(index)
+            }
+        }
+        // If neither ID nor tag is provided, return an error
+        (None, None) => {
+            return Err(CodevaultError::MissingSelector);
+        }
+    };
+
+    // Keep the snippet as it was, to tell whether the edit changed anything
+    let original = snippet_to_edit.clone();
+
+    // Update only the fields given on the command line and save right away
+    if !fields.is_empty() {
+        if let Some(language) = &fields.language {
+            snippet_to_edit.language = Some(canonical_language(language));
+        }
+        if let Some(description) = &fields.description {
+            snippet_to_edit.description = Some(description.trim().to_string());
+        }
+        if let Some(theme) = &fields.theme {
+            snippet_to_edit.theme = Some(theme.clone()).filter(|theme| !theme.is_empty());
+        }
+        return save_edited_snippet(ctx, snippets, original, snippet_to_edit, file_path, dry_run);
+    }
+
+    // The prompts below can't be answered quietly, the fields have to be given instead
+    if ctx.quiet {
+        return Err(CodevaultError::MissingArgument(
+            "--language, --description or --theme",
+        ));
+    }
+
+    // Display current snippet details to the user
+    eprintln!(
+        "{}",
+        spaced(&format!("\n{}\n", style::header("Edit snippet:")))
+    );
+
+    let id_line = detail_line("ID", snippet_to_edit.id);
+    let created_line = detail_line("Created", format_timestamp(&snippet_to_edit.timestamp));
+    let tag_line = detail_line("Current Snippet's Tag", snippet_to_edit.tags_display());
+    let description_line = match &snippet_to_edit.description {
+        Some(desc) => detail_line("Current Description", desc),
+        None => String::new(),
+    };
+
+    // Find the width of the longest line for formatting purposes
+    let longest_line = [&id_line, &created_line, &tag_line, &description_line]
+        .iter()
+        .map(|line| strip_ansi_codes(line).width())
+        .max()
+        .unwrap_or(0);
+
+    // Create the top and bottom borders based on the longest line
+    let top_border = style::border(format!("╔{}╗", "═".repeat(longest_line)));
+    let bottom_border = style::border(format!("╚{}╝", "═".repeat(longest_line)));
+
+    // Print the snippet details with a formatted border
+    eprintln!("{}", top_border);
+    eprintln!("{}", id_line);
+    eprintln!("{}", tag_line);
+    eprintln!("{}", created_line);
+    if !description_line.is_empty() {
+        eprintln!("{}", description_line);
+    }
+    eprintln!("{}", bottom_border);
+    eprintln!("{}", top_border);
+
+    // Prompt user for new tag, description, and language, typing ':q' at any prompt cancels the edit
+    eprint!("{}", edit_question("Enter new tags, comma-separated"));
+    io::stderr().flush().unwrap();
+    let new_tag = read_answer().unwrap_or_default();
+    if is_cancel(&new_tag) {
+        return Ok(EditOutcome::Cancelled);
+    }
+    if !new_tag.trim().is_empty() {
+        snippet_to_edit.tag = parse_tags(&new_tag);
+    }
+
+    eprint!("{}", edit_question("Enter new description"));
+    io::stderr().flush().unwrap();
+    let new_description = read_answer().unwrap_or_default();
+    if is_cancel(&new_description) {
+        return Ok(EditOutcome::Cancelled);
+    }
+    if !new_description.trim().is_empty() {
+        snippet_to_edit.description = Some(new_description.trim().to_string());
+    }
+
+    eprint!("{}", edit_question("Enter new language"));
+    io::stderr().flush().unwrap();
+    let new_language = read_answer().unwrap_or_default();
+    if is_cancel(&new_language) {
+        return Ok(EditOutcome::Cancelled);
+    }
+    if !new_language.trim().is_empty() {
+        snippet_to_edit.language = Some(canonical_language(&new_language));
+    }
+
+    eprintln!("{}", bottom_border);
+    // Read the new code from the editor, or show the current code and read it from user input
+    let new_code = if use_editor {
+        open_in_editor(&snippet_to_edit.code)?
+    } else {
+        eprintln!("\n  {}\n", style::label("Current Code:"));
+
+        // Print the current code with syntax highlighting
+        let highlighted_code = if let Some(lang) = &snippet_to_edit.language {
+            highlight_code_snippets(
+                &snippet_to_edit.code,
+                lang,
+                snippet_to_edit.theme.as_deref(),
+                &ctx.config.theme,
+            )
+        } else {
+            snippet_to_edit.code.clone()
+        };
+        eprintln!("  {}", highlighted_code);
+
+        eprintln!("{}", top_border);
+        eprintln!(
+            "{}{}{}{}{}",
+            style::prompt(" Enter your code snippet (press "),
+            style::label("'Return'"),
+            style::prompt(", then "),
+            style::label("'Ctrl+D'"),
+            style::prompt(" to finish):")
+        );
+        eprintln!(
+            "{} {} {}{}{}",
+            style::error(" Note:"),
+            style::label("'Arrow Keys'"),
+            style::prompt("are not captured, use "),
+            style::label("'Backspace'"),
+            style::prompt(" to erase inputs")
+        );
+        eprintln!(
+            "{}{}{}",
+            style::prompt(" Leave it empty to keep the current code, or type "),
+            style::label("':q'"),
+            style::prompt(" on the first line to cancel the edit")
+        );
+        eprintln!("{}", bottom_border);
+        io::stderr().flush().unwrap();
+
+        // Read the new code snippet from the user input
+        let mut new_code = String::new();
+        io::stdin()
+            .read_to_string(&mut new_code)
+            .map_err(CodevaultError::io("reading snippet from input"))?;
+        new_code
+    };
+
+    // Cancel the whole edit when the first line of code is the ':q' sentinel
+    if is_cancel(new_code.lines().next().unwrap_or("")) {
+        return Ok(EditOutcome::Cancelled);
+    }
+
+    // Update the snippet with the new code, keeping the current code when nothing was entered
+    if !new_code.trim().is_empty() {
+        snippet_to_edit.code = new_code;
+    }
+
+    save_edited_snippet(ctx, snippets, original, snippet_to_edit, file_path, dry_run)
+}
+
+// Function to build the question asked for each field while editing interactively
+fn edit_question(question: &str) -> String {
+    format!(
+        "{}{}{}",
+        style::prompt(format!("  {} (", question)),
+        style::label("leave blank to keep current, ':q' to cancel"),
+        style::prompt("): ")
+    )
+}
+
+// Function to check whether the user typed the ':q' sentinel to cancel an edit
+fn is_cancel(input: &str) -> bool {
+    input.trim() == ":q"
+}
+
+fn save_edited_snippet(
+    ctx: &Context,
+    mut snippets: Vec<Snippet>,
+    original: Snippet,
+    mut snippet: Snippet,
+    file_path: &str,
+    dry_run: bool,
+) -> Result<EditOutcome, CodevaultError> {
+    // Leave the file alone when the edit ended up changing nothing
+    if snippet == original {
+        return Ok(EditOutcome::Unchanged);
+    }
+
+    // Record the modification time and keep the previous code in the history
+    snippet.remember_version(&original);
+    snippet.modified = Some(Local::now());
+
+    // On a dry run, show the snippet as it would be saved and leave the file untouched
+    if dry_run {
+        println!(
+            "{}",
+            spaced(&format!(
+                "\n{}\n",
+                style::header("The snippet would be saved as:")
+            ))
+        );
+        let display = DisplayOptions {
+            theme: ctx.config.theme.clone(),
+            ..DisplayOptions::default()
+        };
+        print_snippet(&snippet, &display);
+        return Ok(EditOutcome::Saved);
+    }
+
+    snippets.push(snippet);
+    save_snippets_for_edit(ctx, snippets, file_path)?;
+    Ok(EditOutcome::Saved)
+}
+
+// Function to save the updated list of snippets to a file, giving duplicate IDs found on load
+// new ones on the way, so any command that changes the collection also repairs it
+fn save_snippets_for_edit(
+    ctx: &Context,
+    mut snippets: Vec<Snippet>,
+    file_path: &str,
+) -> Result<(), CodevaultError> {
+    for (old, new) in renumber_duplicate_ids(&mut snippets) {
+        eprintln!(
+            "{}",
+            paint(&format!(
+                "\x1b[1;32mA duplicate of ID {} is now ID {}.\x1b[0m",
+                old, new
+            ))
+        );
+    }
+
+    // Replace the file with the updated snippets in a single atomic step
+    write_json_atomically(ctx, &snippets, file_path)
+}
+
+// Function to print a unified diff of the code of two snippets inside a box, removed lines in red
+// and added lines in green. Returns whether the code differs at all.
+pub fn diff_snippets(
+    ctx: &Context,
+    file_path: &str,
+    old_id: u32,
+    new_id: u32,
+) -> Result<bool, CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+    check_ids_exist(&snippets, &[old_id, new_id])?;
+    let find = |id: u32| {
+        snippets
+            .iter()
+            .find(|s| s.id == id)
+            .ok_or(CodevaultError::NotFound(id))
+    };
+
+    let sections = diff_sections(find(old_id)?, find(new_id)?);
+    if sections.is_empty() {
+        println!(
+            "{}",
+            paint(&format!(
+                "\x1b[1;33mNo differences, snippets {} and {} have the same code.\x1b[0m",
+                old_id, new_id
+            ))
+        );
+        return Ok(false);
+    }
+    print_box(&sections);
+    Ok(true)
+}
+
+// Function to lay out the diff of two snippets, a header naming them and then one section per
+// hunk with 3 lines of context. Empty when the code is the same.
+fn diff_sections(old: &Snippet, new: &Snippet) -> Vec<Vec<String>> {
+    let old_code = expand_tabs(&old.code, 4);
+    let new_code = expand_tabs(&new.code, 4);
+    if old_code == new_code {
+        return Vec::new();
+    }
+    let diff = similar::TextDiff::from_lines(&old_code, &new_code);
+
+    let name = |snippet: &Snippet| {
+        format!(
+            "ID {}{}",
+            snippet.id,
+            if snippet.tag.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", snippet.tags_display())
+            }
+        )
+    };
+    let mut sections = vec![vec![
+        format!(
+            "  {}",
+            style::styled(style::REMOVED.bold(), format!("--- {}", name(old)))
+        ),
+        format!(
+            "  {}",
+            style::styled(style::ADDED.bold(), format!("+++ {}", name(new)))
+        ),
+    ]];
+
+    let mut unified = diff.unified_diff();
+    for hunk in unified.context_radius(3).iter_hunks() {
+        let mut lines = vec![format!("  {}", style::hunk(hunk.header()))];
+        for change in hunk.iter_changes() {
+            let text = change.value().trim_end_matches(['\r', '\n']);
+            lines.push(match change.tag() {
+                similar::ChangeTag::Delete => format!("  {}", style::removed(format!("-{}", text))),
+                similar::ChangeTag::Insert => format!("  {}", style::added(format!("+{}", text))),
+                similar::ChangeTag::Equal => format!("   {}", text),
+            });
+        }
+        sections.push(lines);
+    }
+    sections
+}
+
+// Function to append the code of one snippet to another, below a comment naming where it came from.
+// The source snippet is kept unless asked to remove it, in which case it goes to the trash.
+pub fn merge_snippets(
+    ctx: &Context,
+    file_path: &str,
+    into: u32,
+    from: u32,
+    remove_source: bool,
+) -> Result<(), CodevaultError> {
+    if into == from {
+        return Err(CodevaultError::MergeIntoItself(into));
+    }
+    let mut snippets = load_snippets(ctx, file_path)?;
+    check_ids_exist(&snippets, &[into, from])?;
+
+    let source = snippets
+        .iter()
+        .find(|s| s.id == from)
+        .cloned()
+        .ok_or(CodevaultError::NotFound(from))?;
+    let target = snippets
+        .iter_mut()
+        .find(|s| s.id == into)
+        .ok_or(CodevaultError::NotFound(into))?;
+    let original = target.clone();
+
+    // Keep a blank line and a comment in the target's language between the two pieces of code
+    let separator = line_comment(
+        target.language.as_deref(),
+        &format!("merged from snippet {}", from),
+    );
+    let mut code = target.code.trim_end_matches('\n').to_string();
+    code.push_str(&format!("\n\n{}\n{}", separator, source.code));
+    if !code.ends_with('\n') {
+        code.push('\n');
+    }
+    target.code = code;
+    target.remember_version(&original);
+    target.modified = Some(Local::now());
+
+    // Save the merged target before the source goes anywhere, so a failed save loses nothing
+    if !remove_source {
+        return save_snippets_for_edit(ctx, snippets, file_path);
+    }
+    save_snippets_for_edit(ctx, snippets.clone(), file_path)?;
+
+    // Then move the source to the trash, so `undo` can bring it back, and only then drop it
+    snippets.retain(|s| s.id != from);
+    move_to_trash(ctx, file_path, vec![source])?;
+    save_snippets_for_edit(ctx, snippets, file_path)
+}
+
+// Function to add the snippets of a JSON export to the collection, as an array or a single snippet.
+// Every entry has to be a valid snippet unless lenient, then the invalid ones are skipped instead.
+// Imported snippets get new IDs after the existing ones, so they never clash.
+pub fn import_snippets(
+    ctx: &Context,
+    file_path: &str,
+    content: &str,
+    origin: &str,
+    lenient: bool,
+) -> Result<ImportSummary, CodevaultError> {
+    let invalid = |message: String| CodevaultError::InvalidImport {
+        origin: origin.to_string(),
+        message,
+    };
+
+    // Syntax errors are reported with the line and column serde stopped at
+    let value: serde_json::Value =
+        serde_json::from_str(content).map_err(|err| invalid(err.to_string()))?;
+    let entries = match value {
+        serde_json::Value::Array(entries) => entries,
+        entry @ serde_json::Value::Object(_) => vec![entry],
+        _ => {
+            return Err(invalid(
+                "expected a snippet or an array of snippets".to_string(),
+            ))
+        }
+    };
+
+    // Check every entry on its own, so one bad entry doesn't hide the others
+    let mut summary = ImportSummary::default();
+    let mut valid = Vec::new();
+    let mut first_malformed = None;
+    for (index, entry) in entries.into_iter().enumerate() {
+        match serde_json::from_value::<Snippet>(entry) {
+            Ok(snippet) if snippet.code.trim().is_empty() => {
+                summary
+                    .skipped
+                    .push((index, "the code is empty".to_string()));
+            }
+            Ok(snippet) => valid.push(snippet),
+            Err(err) => {
+                first_malformed.get_or_insert(index);
+                summary.skipped.push((index, err.to_string()));
+            }
+        }
+    }
+
+    // Without --lenient nothing is imported, pointing at the first bad entry in the text
+    if let Some((index, reason)) = summary.skipped.first().filter(|_| !lenient) {
+        // Parsing the text straight into snippets stops where that entry goes wrong
+        let located = if content.trim_start().starts_with('[') {
+            serde_json::from_str::<Vec<Snippet>>(content).err()
+        } else {
+            serde_json::from_str::<Snippet>(content).err()
+        };
+        let position = match located {
+            Some(err) if first_malformed == Some(*index) => {
+                format!(" at line {}, column {}", err.line(), err.column())
+            }
+            _ => String::new(),
+        };
+        return Err(invalid(format!(
+            "entry {}{}: {}, pass --lenient to import the valid entries only",
+            index + 1,
+            position,
+            reason
+        )));
+    }
+
+    let mut snippets: Vec<Snippet> = if Path::new(file_path).exists() {
+        load_snippets(ctx, file_path)?
+    } else {
+        Vec::new()
+    };
+    let first_id = snippets.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+    for (id, mut snippet) in (first_id..).zip(valid) {
+        snippet.id = id;
+        snippet.language = snippet
+            .language
+            .map(|language| canonical_language(&language));
+        summary.imported.push(id);
+        snippets.push(snippet);
+    }
+
+    if !summary.imported.is_empty() {
+        save_snippets_for_edit(ctx, snippets, file_path)?;
+    }
+    Ok(summary)
+}
+
+// Function to pin or unpin a snippet based on its ID
+pub fn set_pinned(
+    ctx: &Context,
+    file_path: &str,
+    id: u32,
+    pinned: bool,
+) -> Result<(), CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    // Find the snippet with the given ID and update its pin
+    let snippet = snippets
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or(CodevaultError::NotFound(id))?;
+    snippet.pinned = pinned;
+
+    save_snippets_for_edit(ctx, snippets, file_path)
+}
+
+// Function to get a snippet with the previous versions of its code, newest first
+pub fn snippet_history(ctx: &Context, file_path: &str, id: u32) -> Result<Snippet, CodevaultError> {
+    load_snippets(ctx, file_path)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or(CodevaultError::NotFound(id))
+}
+
+// Function to bring back a previous version of a snippet's code, numbered from 1 for the newest,
+// the code it replaces is kept in the history so the restore can be undone the same way
+pub fn restore_version(
+    ctx: &Context,
+    file_path: &str,
+    id: u32,
+    version: usize,
+) -> Result<(), CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+    let snippet = snippets
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or(CodevaultError::NotFound(id))?;
+    let restored = version
+        .checked_sub(1)
+        .and_then(|index| snippet.history.get(index))
+        .ok_or(CodevaultError::VersionNotFound { id, version })?
+        .code
+        .clone();
+
+    let original = snippet.clone();
+    snippet.code = restored;
+    snippet.remember_version(&original);
+    snippet.modified = Some(Local::now());
+
+    save_snippets_for_edit(ctx, snippets, file_path)
+}
+
+// Function to find the snippets whose stored language syntect doesn't know, they are highlighted as plain text
+pub fn snippets_with_unknown_language(
+    ctx: &Context,
+    file_path: &str,
+) -> Result<Vec<Snippet>, CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+    Ok(snippets
+        .into_iter()
+        .filter(|s| {
+            s.language
+                .as_deref()
+                .is_some_and(|language| resolve_language(language).is_none())
+        })
+        .collect())
+}
+
+// Function to set the language of a snippet, or clear it so the snippet is plain text
+pub fn set_language(
+    ctx: &Context,
+    file_path: &str,
+    id: u32,
+    language: Option<&str>,
+) -> Result<(), CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    let snippet = snippets
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or(CodevaultError::NotFound(id))?;
+    snippet.language = language.map(canonical_language);
+    snippet.modified = Some(Local::now());
+
+    save_snippets_for_edit(ctx, snippets, file_path)
+}
+
+// Function to add code to the end of an existing snippet, on a line of its own
+pub fn append_to_snippet(
+    ctx: &Context,
+    file_path: &str,
+    id: u32,
+    code: &str,
+) -> Result<(), CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+    let snippet = snippets
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or(CodevaultError::NotFound(id))?;
+    let original = snippet.clone();
+
+    // Separate the new code from the existing code with a newline
+    if !snippet.code.is_empty() && !snippet.code.ends_with('\n') {
+        snippet.code.push('\n');
+    }
+    snippet.code.push_str(code);
+    snippet.remember_version(&original);
+    snippet.modified = Some(Local::now());
+
+    save_snippets_for_edit(ctx, snippets, file_path)
+}
+
+// Function to save a copy of a snippet under a fresh ID, returning the new ID
+pub fn duplicate_snippet(ctx: &Context, file_path: &str, id: u32) -> Result<u32, CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+    let original = snippets
+        .iter()
+        .find(|s| s.id == id)
+        .ok_or(CodevaultError::NotFound(id))?;
+
+    // The copy starts its own history, it is new, unpinned and never copied
+    let copy = Snippet {
+        id: generate_unique_id(ctx, file_path)?,
+        timestamp: Local::now(),
+        modified: None,
+        pinned: false,
+        copy_count: 0,
+        history: Vec::new(),
+        ..original.clone()
+    };
+    let new_id = copy.id;
+
+    snippets.push(copy);
+    save_snippets_for_edit(ctx, snippets, file_path)?;
+    Ok(new_id)
+}
+
+// Function to let the user pick a snippet with an interactive fuzzy selector, None when cancelled
+pub fn pick_snippet(ctx: &Context, file_path: &str) -> Result<Option<u32>, CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+    if snippets.is_empty() {
+        return Err(CodevaultError::EmptyCollection);
+    }
+
+    // One line per snippet with the tags, description and first line of code to search through
+    let items: Vec<String> = snippets
+        .iter()
+        .map(|snippet| {
+            let first_line = snippet
+                .code
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("")
+                .trim();
+            format!(
+                "#{} [{}] {} | {}",
+                snippet.id,
+                snippet.tags_display(),
+                snippet.description.as_deref().unwrap_or(""),
+                first_line
+            )
+        })
+        .collect();
+
+    // The selector draws on stderr, so the copied code can still be redirected from stdout
+    let selection = FuzzySelect::new()
+        .with_prompt("Type to search, Enter to copy, Esc to cancel")
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .map_err(|dialoguer::Error::IO(err)| CodevaultError::io("reading the selection")(err))?;
+
+    Ok(selection.map(|index| snippets[index].id))
+}
+
+// Function to rename a tag on every snippet carrying it, saving the collection once
+pub fn retag_snippets(
+    ctx: &Context,
+    file_path: &str,
+    from: &str,
+    to: &str,
+) -> Result<usize, CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    let modified = Local::now();
+    let mut updated = 0;
+    for snippet in snippets.iter_mut() {
+        if snippet.retag(from, to) {
+            snippet.modified = Some(modified);
+            updated += 1;
+        }
+    }
+
+    // Report an error when no snippet has the tag, leaving the file untouched
+    if updated == 0 {
+        return Err(CodevaultError::NoMatch(from.trim().to_string()));
+    }
+
+    save_snippets_for_edit(ctx, snippets, file_path)?;
+    Ok(updated)
+}
+
+// Function to rewrite the tags of every snippet in one case, returning how many snippets changed
+pub fn normalize_snippet_tags(
+    ctx: &Context,
+    file_path: &str,
+    case: TagCase,
+) -> Result<usize, CodevaultError> {
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    let modified = Local::now();
+    let mut updated = 0;
+    for snippet in snippets.iter_mut() {
+        if snippet.normalize_tags(case) {
+            snippet.modified = Some(modified);
+            updated += 1;
+        }
+    }
+
+    // Leave the file untouched when the tags are already consistent
+    if updated > 0 {
+        save_snippets_for_edit(ctx, snippets, file_path)?;
+    }
+    Ok(updated)
+}
+
+// Function to copy a snippet based on its ID. With count_copy the copy is added to the snippet's
+// copy count, a failure to save it is only warned about since the code is already there.
+pub fn copy_code(
+    ctx: &Context,
+    file_path: &str,
+    id: &Option<u32>,
+    count_copy: bool,
+) -> Result<Snippet, CodevaultError> {
+    // Load snippets from the file
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    // Check if ID is provided
+    let Some(id) = *id else {
+        return Err(CodevaultError::MissingId);
+    };
+
+    // Find the snippet with the given ID, count the copy and return it
+    let Some(snippet) = snippets.iter_mut().find(|s| s.id == id) else {
+        // Return an error if the snippet ID is not found
+        return Err(CodevaultError::NotFound(id));
+    };
+    if !count_copy {
+        return Ok(snippet.clone());
+    }
+    snippet.copy_count += 1;
+    let snippet = snippet.clone();
+    if let Err(err) = save_snippets_for_edit(ctx, snippets, file_path) {
+        eprintln!(
+            "{}",
+            paint(&format!(
+                "\n\x1b[1;33mwarning:\x1b[0m the copy of snippet {} could not be counted: {}",
+                id, err
+            ))
+        );
+    }
+    Ok(snippet)
+}
+
+// Function to delete snippets based on their IDs
+pub fn matching_snippet_ids(
+    ctx: &Context,
+    file_path: &str,
+    tag: &Option<String>,
+    language: &Option<String>,
+    exact: bool,
+) -> Result<Vec<u32>, CodevaultError> {
+    // Load the existing snippets from the file
+    let snippets = load_snippets(ctx, file_path)?;
+
+    // Collect the IDs of snippets matching both selectors, using the same matching as view and export
+    let ids: Vec<u32> = snippets
+        .iter()
+        .filter(|s| tag.as_ref().is_none_or(|tag| s.matches_tags(tag, exact)))
+        .filter(|s| {
+            language
+                .as_ref()
+                .is_none_or(|lang| s.matches_languages(lang, exact))
+        })
+        .map(|s| s.id)
+        .collect();
+
+    // Report an error when the selectors match nothing
+    if ids.is_empty() {
+        let selectors = [tag.as_deref(), language.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("' and '");
+        return Err(CodevaultError::NoMatch(selectors));
+    }
+
+    Ok(ids)
+}
+
+// Function to report the given IDs that are not in the collection, naming all of them
+fn check_ids_exist(snippets: &[Snippet], ids: &[u32]) -> Result<(), CodevaultError> {
+    // Vector to hold IDs that do not exist in the current snippets
+    let non_existent_ids: Vec<u32> = ids
+        .iter()
+        .filter(|id| !snippets.iter().any(|s| s.id == **id))
+        .copied()
+        .collect();
+
+    // If there are non-existent IDs, return an error with their details
+    match non_existent_ids.as_slice() {
+        [] => Ok(()),
+        [id] => Err(CodevaultError::NotFound(*id)),
+        _ => Err(CodevaultError::NotFoundMany(non_existent_ids)),
+    }
+}
+
+pub fn delete_snippet(
+    ctx: &Context,
+    file_path: &str,
+    ids: &[u32],
+    dry_run: bool,
+) -> Result<(), CodevaultError> {
+    // Load the existing snippets from the file
+    let mut snippets = load_snippets(ctx, file_path)?;
+
+    // Refuse the whole deletion if any of the IDs does not exist
+    check_ids_exist(&snippets, ids)?;
+
+    // Show what each targeted snippet is, so the right ones get deleted
+    if !ctx.quiet {
+        println!(
+            "{}",
+            paint(spaced("\n\x1b[38;5;201;1mDelete snippet:\x1b[0m\n"))
+        );
+        for snippet in snippets.iter().filter(|s| ids.contains(&s.id)) {
+            print_snippet_summary(snippet, &ctx.config.theme);
+        }
+    }
+
+    // On a dry run, stop before anything is removed
+    if dry_run {
+        println!(
+            "{}",
+            paint(&format!(
+                "\x1b[1;33mDry run, {} {} would be deleted and nothing was changed.\x1b[0m",
+                ids.len(),
+                if ids.len() == 1 {
+                    "snippet"
+                } else {
+                    "snippets"
+                }
+            ))
+        );
+        return Ok(());
+    }
+
+    // Prepare a string of IDs for confirmation prompt
+    let ids_str = ids
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut plural = "snippet";
+    if ids.len() > 1 {
+        plural = "snippets";
+    }
+    // Prompt user for confirmation
+    if !prompt_yes_no(
+        ctx,
+        &style::prompt(format!(
+            "Are you sure you want to permanently delete {} {}?",
+            plural, ids_str
+        )),
+        false,
+    )? {
+        println!("{}", paint("\n\x1b[91mSnippet deletion cancelled\x1b[0m"));
+        return Ok(());
+    }
+
+    // Remove snippets with the specified IDs
+    let mut removed = Vec::new();
+    for id in ids {
+        if let Some(index) = snippets.iter().position(|s| s.id == *id) {
+            removed.push(snippets.remove(index));
+        }
+    }
+
+    // Move the removed snippets to the trash first, then save the remaining snippets back to the file
+    if !removed.is_empty() {
+        move_to_trash(ctx, file_path, removed)?;
+        save_snippets_for_edit(ctx, snippets, file_path)?;
+        ctx.announce("\n\x1b[32mdeleted successfully!\x1b[0m \x1b[2m(run `codevault undo` to restore)\x1b[0m");
+    }
+
+    Ok(())
+}
+
+// Function to delete every snippet of the collection, only after the user types DELETE
+pub fn delete_all_snippets(
+    ctx: &Context,
+    file_path: &str,
+    dry_run: bool,
+) -> Result<(), CodevaultError> {
+    let snippets = load_snippets(ctx, file_path)?;
+    if snippets.is_empty() {
+        return Err(CodevaultError::EmptyCollection);
+    }
+    let count = snippets.len();
+    let plural = if count == 1 { "snippet" } else { "snippets" };
+
+    // On a dry run, stop before anything is removed
+    if dry_run {
+        println!(
+            "{}",
+            paint(&format!(
+                "\x1b[1;33mDry run, all {} {} would be deleted and nothing was changed.\x1b[0m",
+                count, plural
+            ))
+        );
+        return Ok(());
+    }
+
+    // Only part of a large collection fits in the trash, say so before it is gone
+    if count > TRASH_LIMIT {
+        println!(
+            "{}",
+            paint(&format!(
+                "\n\x1b[1;33mOnly the last {} of the {} snippets can be restored with `codevault undo`.\x1b[0m",
+                TRASH_LIMIT, count
+            ))
+        );
+    }
+
+    // A plain 'y' is too easy to type by accident for this, the word has to be typed out
+    let answer = if ctx.assume_yes {
+        "DELETE".to_string()
+    } else if ctx.quiet {
+        String::new()
+    } else {
+        prompt(&format!(
+            "\n{}{}{}",
+            style::prompt(format!(
+                "This deletes all {} {} in the collection. Type ",
+                count, plural
+            )),
+            style::label("DELETE"),
+            style::prompt(" to confirm: ")
+        ))
+    };
+    // Piped input that ran out didn't answer at all, like the other confirmations this needs --yes
+    if answer.is_empty() && !ctx.quiet && !io::stdin().is_terminal() {
+        return Err(CodevaultError::ConfirmationRequired);
+    }
+    if answer != "DELETE" {
+        println!("{}", paint("\n\x1b[91mSnippet deletion cancelled\x1b[0m"));
+        return Ok(());
+    }
+
+    move_to_trash(ctx, file_path, snippets)?;
+    save_snippets_for_edit(ctx, Vec::new(), file_path)?;
+    ctx.announce(&format!(
+        "\n\x1b[32mAll {} {} deleted successfully!\x1b[0m \x1b[2m(run `codevault undo` to restore)\x1b[0m",
+        count, plural
+    ));
+
+    Ok(())
+}
+
+// Function to get the trash file stored next to the data file, e.g. data/codevault.trash.json
+fn trash_path(file_path: &str) -> String {
+    Path::new(file_path)
+        .with_extension("trash.json")
+        .to_string_lossy()
+        .to_string()
+}
+
+fn load_trash(ctx: &Context, trash_file: &str) -> Result<Vec<TrashEntry>, CodevaultError> {
+    // A missing trash file simply means nothing has been deleted yet
+    if !Path::new(trash_file).exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = read_vault_file(ctx, trash_file)?;
+    serde_json::from_slice(&content).map_err(|source| CodevaultError::Deserialize {
+        path: trash_file.to_string(),
+        source,
+    })
+}
+
+fn move_to_trash(
+    ctx: &Context,
+    file_path: &str,
+    removed: Vec<Snippet>,
+) -> Result<(), CodevaultError> {
+    let trash_file = trash_path(file_path);
+    let mut trash = load_trash(ctx, &trash_file)?;
+
+    // Every delete gets its own batch number so `undo` can restore it as a whole
+    let batch = trash.iter().map(|entry| entry.batch).max().unwrap_or(0) + 1;
+    let deleted = Local::now();
+    trash.extend(removed.into_iter().map(|snippet| TrashEntry {
+        batch,
+        deleted,
+        snippet,
+    }));
+
+    // Only keep the most recent entries
+    if trash.len() > TRASH_LIMIT {
+        trash.drain(..trash.len() - TRASH_LIMIT);
+    }
+
+    write_json_atomically(ctx, &trash, &trash_file)
+}
+
+pub fn undo_delete(ctx: &Context, file_path: &str) -> Result<Vec<u32>, CodevaultError> {
+    let trash_file = trash_path(file_path);
+    let trash = load_trash(ctx, &trash_file)?;
+
+    // Find the most recently deleted batch
+    let Some(batch) = trash.iter().map(|entry| entry.batch).max() else {
+        return Err(CodevaultError::EmptyTrash);
+    };
+    let (restored, remaining): (Vec<TrashEntry>, Vec<TrashEntry>) =
+        trash.into_iter().partition(|entry| entry.batch == batch);
+
+    // Load the current collection, if the file does not exist start with an empty vector
+    let mut snippets: Vec<Snippet> = if Path::new(file_path).exists() {
+        load_snippets(ctx, file_path)?
+    } else {
+        Vec::new()
+    };
+
+    // Give the restored snippets fresh IDs, their old ones may have been reused since
+    let first_id = snippets.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+    let mut restored_ids = Vec::new();
+    for (id, entry) in (first_id..).zip(restored) {
+        let mut snippet = entry.snippet;
+        snippet.id = id;
+        restored_ids.push(id);
+        snippets.push(snippet);
+    }
+
+    // Save the collection before dropping the batch from the trash, so nothing is lost on failure
+    save_snippets_for_edit(ctx, snippets, file_path)?;
+    write_json_atomically(ctx, &remaining, &trash_file)?;
+
+    Ok(restored_ids)
+}
+
+pub fn list_trash(ctx: &Context, file_path: &str) -> Result<(), CodevaultError> {
+    let trash = load_trash(ctx, &trash_path(file_path))?;
+    if trash.is_empty() {
+        println!("{}", paint("\n\x1b[1;33mThe trash is empty.\x1b[0m"));
+        return Ok(());
+    }
+
+    // Print the batches from newest to oldest, the first one is what `undo` restores
+    let mut batches: Vec<u32> = trash.iter().map(|entry| entry.batch).collect();
+    batches.sort_unstable_by(|a, b| b.cmp(a));
+    batches.dedup();
+    for batch in batches {
+        let entries: Vec<&TrashEntry> = trash.iter().filter(|e| e.batch == batch).collect();
+        println!(
+            "{}",
+            paint(spaced(&format!(
+                "\n\x1b[38;5;201;1mDeleted {}:\x1b[0m\n",
+                format_timestamp(&entries[0].deleted)
+            )))
+        );
+        for entry in entries {
+            print_snippet_summary(&entry.snippet, &ctx.config.theme);
+        }
+    }
+
+    Ok(())
+}
+
+// Function to map a language name to the file extension used when exporting,
+// taken from the first extension syntect lists for the language
+fn language_extension(language: Option<&str>) -> &'static str {
+    language
+        .and_then(resolve_language)
+        .and_then(|syntax| syntax.file_extensions.first())
+        .map(|extension| extension.as_str())
+        .unwrap_or("txt") // Default extension for unknown languages
+}
+
+// Function to export snippets based on filters
+pub fn export_snippets(
+    ctx: &Context,
+    file_path: &str,
+    ids: &[u32],
+    tag: &Option<String>,
+    language: &Option<String>,
+    options: &ExportOptions,
+) -> Result<(), CodevaultError> {
+    // Snippets saved while exporting are newer than the marker, so they go into the next export
+    let started = Local::now();
+    let since = if options.since_last {
+        read_last_export(file_path)?
+    } else {
+        None
+    };
+
+    // Move the marker forward only when every selected snippet was written, so the ones left out
+    // by a cancel or a declined overwrite are still in the next export
+    if export_selected(ctx, file_path, ids, tag, language, since, options)? && options.since_last {
+        write_last_export(file_path, &started)?;
+    }
+    Ok(())
+}
+
+// Function to get the file remembering when `export --since-last` last ran, next to the data file
+fn last_export_path(file_path: &str) -> PathBuf {
+    Path::new(file_path).with_extension("last-export")
+}
+
+// Function to read when `export --since-last` last ran, None before the first such export
+fn read_last_export(file_path: &str) -> Result<Option<DateTime<Local>>, CodevaultError> {
+    let path = last_export_path(file_path);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(CodevaultError::io(format!(
+                "reading file '{}'",
+                path.display()
+            ))(err))
+        }
+    };
+    DateTime::parse_from_rfc3339(content.trim())
+        .map(|time| Some(time.with_timezone(&Local)))
+        .map_err(|err| {
+            CodevaultError::io(format!("reading file '{}'", path.display()))(io::Error::new(
+                io::ErrorKind::InvalidData,
+                err,
+            ))
+        })
+}
+
+// Function to remember when `export --since-last` ran, for the next one to continue from there.
+// Written like the data file, so an interrupted write can't leave a half-written time behind.
+fn write_last_export(file_path: &str, time: &DateTime<Local>) -> Result<(), CodevaultError> {
+    let path = last_export_path(file_path);
+    write_file_atomically(
+        (time.to_rfc3339() + "\n").as_bytes(),
+        &path.to_string_lossy(),
+    )
+}
+
+// Function to export the snippets matching the filters, returning false when the user cancelled
+// or some of them were not written because their file already existed
+fn export_selected(
+    ctx: &Context,
+    file_path: &str,
+    ids: &[u32],
+    tag: &Option<String>,
+    language: &Option<String>,
+    since: Option<DateTime<Local>>,
+    options: &ExportOptions,
+) -> Result<bool, CodevaultError> {
+    let ExportOptions {
+        path: export_path,
+        output,
+        format,
+        force,
+        plain,
+        stdout,
+        line_endings,
+        verbose,
+        exact,
+        since_last: _,
+    } = options;
+
+    // Load the existing snippets from the file
+    let snippets = load_snippets(ctx, file_path)?;
+
+    // Start with all snippets and apply filters
+    let mut filtered_snippets: Vec<Snippet> = snippets.clone();
+
+    // Filter by snippet IDs if provided, every one of them has to exist
+    if !ids.is_empty() {
+        check_ids_exist(&snippets, ids)?;
+        filtered_snippets = filtered_snippets
+            .into_iter()
+            .filter(|s| ids.contains(&s.id))
+            .collect::<Vec<_>>();
+    }
+
+    // Filter by tag if provided
+    if let Some(tag) = tag {
+        filtered_snippets = filtered_snippets
+            .into_iter()
+            .filter(|s| s.matches_tags(tag, *exact))
+            .collect::<Vec<_>>();
+    }
+
+    // Filter by language if provided
+    if let Some(lang) = language {
+        filtered_snippets = filtered_snippets
+            .into_iter()
+            .filter(|s| s.matches_languages(lang, *exact))
+            .collect::<Vec<_>>();
+    }
+
+    // Check if any snippets match the filter criteria
+    if filtered_snippets.is_empty() {
+        let selectors = [tag.as_deref(), language.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("' and '");
+        if !selectors.is_empty() {
+            return Err(CodevaultError::NoMatch(selectors));
+        }
+    }
+
+    // Leave out the snippets that haven't changed since the last export
+    if let Some(since) = since {
+        filtered_snippets.retain(|s| s.modified.unwrap_or(s.timestamp) > since);
+        if filtered_snippets.is_empty() {
+            ctx.announce(&format!(
+                "\n\x1b[1;36mNo snippets were added or modified since the last export on \x1b[1;33m{}\x1b[0m",
+                format_timestamp(&since)
+            ));
+            return Ok(true);
+        }
+    }
+
+    // Convert the line endings of the exported code, a JSON export keeps the stored code as it is
+    if output.is_none() {
+        for snippet in &mut filtered_snippets {
+            snippet.code = line_endings.apply(&snippet.code);
+        }
+    }
+
+    // Write to stdout instead of files, for redirecting a one-off export
+    if *stdout {
+        let text = match (format, filtered_snippets.as_slice()) {
+            (_, []) => return Err(CodevaultError::EmptyCollection),
+            (ExportFormat::Markdown, snippets) => markdown_document(snippets),
+            // Several snippets make one page, a gallery to share
+            (ExportFormat::Html, snippets) => html_document(ctx, snippets),
+            (ExportFormat::Files, [snippet]) => snippet.code.clone(),
+            (ExportFormat::Files, snippets) => {
+                return Err(CodevaultError::MultipleMatches(snippets.len()))
+            }
+        };
+        let mut out = io::stdout();
+        return out
+            .write_all(text.as_bytes())
+            .and_then(|_| out.flush())
+            .map(|_| true)
+            .map_err(CodevaultError::io("writing snippets to stdout"));
+    }
+
+    // Confirm export if more than one snippet is being exported, quietly it just goes ahead
+    // since existing files are still only overwritten with --force or --yes
+    if filtered_snippets.len() > 1 && !ctx.quiet && !ctx.assume_yes {
+        println!(
+            "{}",
+            paint(spaced("\n\x1b[38;5;201;1mExport Snippets:\x1b[0m\n"))
+        );
+        let destination = match (output, format) {
+            (Some(_), _) => "a single JSON file",
+            (None, ExportFormat::Files) if *plain => "plain text files",
+            (None, ExportFormat::Files) => "language-specific formats",
+            (None, ExportFormat::Markdown) => "a single Markdown file",
+            (None, ExportFormat::Html) => "HTML pages",
+        };
+        if !prompt_yes_no(
+            ctx,
+            &style::prompt(format!(
+                "Exporting {} snippets in {}. Are you sure you want to continue?",
+                filtered_snippets.len(),
+                destination
+            )),
+            false,
+        )? {
+            println!("{}", paint("\n\x1b[1;91mSnippet export cancelled\x1b[0m"));
+            return Ok(false);
+        }
+    }
+
+    // Write the snippets as one JSON array when an output file is given
+    if let Some(output) = output {
+        return export_json(ctx, &filtered_snippets, output, *force);
+    }
+
+    // Determine export directory
+    let export_dir = match export_path {
+        Some(path) => path.clone(),
+        None => {
+            // Fall back to CODEVAULT_EXPORT_DIR or the config file's export_dir, then to 'snippet_exports'
+            let dir = ctx
+                .config
+                .export_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("snippet_exports"));
+            ctx.announce(&format!("\x1b[1m\x1b[36mNo export path specified. Exporting snippets to the default '{}' directory. Please wait...\x1b[0m", dir.display()));
+            dir
+        }
+    };
+
+    // Create the export directory if it does not exist
+    std::fs::create_dir_all(&export_dir).map_err(CodevaultError::io(format!(
+        "creating directory '{}'",
+        export_dir.display()
+    )))?;
+
+    // Export all snippets into a single Markdown file if requested
+    if *format == ExportFormat::Markdown {
+        return export_markdown(ctx, &filtered_snippets, &export_dir, *force);
+    }
+
+    // Create the filename for an exported snippet, using the extension of its language unless plain text is forced
+    let export_filename = |snippet: &Snippet| {
+        let extension = if *format == ExportFormat::Html {
+            "html"
+        } else if *plain {
+            "txt"
+        } else {
+            language_extension(snippet.language.as_deref())
+        };
+        format!("{}/{}.{}", export_dir.display(), snippet.id, extension)
+    };
+
+    // Ask once whether files that already exist should be overwritten, unless forced
+    let existing_files: Vec<String> = filtered_snippets
+        .iter()
+        .map(export_filename)
+        .filter(|filename| Path::new(filename).exists())
+        .collect();
+    let overwrite = *force
+        || match existing_files.as_slice() {
+            [] => false,
+            [filename] => prompt_yes_no(
+                ctx,
+                &style::prompt(format!(
+                    "\nThe file '{}' already exists. Do you want to overwrite it?",
+                    filename
+                )),
+                false,
+            )?,
+            files => prompt_yes_no(
+                ctx,
+                &style::prompt(format!(
+                    "\n{} of the export files already exist. Do you want to overwrite them?",
+                    files.len()
+                )),
+                false,
+            )?,
+        };
+
+    // Report every file on its own when asked to or when there is just one, otherwise show
+    // the progress in place and sum it up at the end
+    let total = filtered_snippets.len();
+    let per_file = *verbose || total == 1;
+    let progress = if per_file || ctx.quiet {
+        ProgressBar::hidden()
+    } else {
+        let template = if color_enabled() {
+            "{bar:30.cyan/blue} exported {pos}/{len}"
+        } else {
+            "{bar:30} exported {pos}/{len}"
+        };
+        ProgressBar::new(total as u64)
+            .with_style(ProgressStyle::with_template(template).expect("valid progress template"))
+    };
+    let mut skipped = 0;
+
+    // Export each snippet to a file
+    for snippet in filtered_snippets {
+        let filename = export_filename(&snippet);
+        progress.inc(1);
+
+        // Skip the file if it already exists and should not be overwritten
+        if !overwrite && Path::new(&filename).exists() {
+            skipped += 1;
+            if per_file {
+                note_kept_file(&filename);
+            }
+            continue;
+        }
+
+        // Create the file and write the snippet code to it
+        let file = File::create(&filename)
+            .map_err(CodevaultError::io(format!("creating file '{}'", filename)))?;
+        let mut writer = BufWriter::new(file);
+
+        let content = match format {
+            ExportFormat::Html => html_document(ctx, std::slice::from_ref(&snippet)),
+            _ => snippet.code,
+        };
+        write!(writer, "{}", content).map_err(CodevaultError::io(format!(
+            "writing to file '{}'",
+            filename
+        )))?;
+
+        // Confirm successful export
+        if per_file {
+            ctx.announce(&format!(
+                "\x1b[1;32m\nSuccessfully exported snippet to file '{}'.\x1b[0m",
+                filename
+            ));
+        }
+    }
+    progress.finish_and_clear();
+
+    if !per_file {
+        if total > skipped {
+            ctx.announce(&format!(
+                "\x1b[1;32m\nSuccessfully exported {} snippets to '{}'.\x1b[0m",
+                total - skipped,
+                export_dir.display()
+            ));
+        }
+        if skipped > 0 {
+            eprintln!(
+                "{}",
+                paint(&format!(
+                    "\x1b[1m\x1b[93m{} {} already exported and left as {} were, use --force to overwrite them.\x1b[0m",
+                    skipped,
+                    if skipped == 1 { "file was" } else { "files were" },
+                    if skipped == 1 { "it" } else { "they" }
+                ))
+            );
+        }
+    }
+
+    Ok(skipped == 0)
+}
+
+// Function to tell the user an export file was kept instead of overwritten. Every such notice
+// goes to stderr, even with --quiet, since stdout may be where the snippets are going.
+fn note_kept_file(path: &dyn std::fmt::Display) {
+    eprintln!(
+        "{}",
+        paint(&format!(
+            "\n\x1b[1;93mThe file has been already exported and is located at '{}'.\x1b[0m",
+            path
+        ))
+    );
+}
+
+// Function to export snippets into a single JSON file, in the same format as the data file.
+// Returns false when the file already exists and the user chose to keep it.
+fn export_json(
+    ctx: &Context,
+    snippets: &[Snippet],
+    output: &Path,
+    force: bool,
+) -> Result<bool, CodevaultError> {
+    // Skip the file if it already exists and the user does not want to overwrite it
+    if !force
+        && output.exists()
+        && !prompt_yes_no(
+            ctx,
+            &style::prompt(format!(
+                "\nThe file '{}' already exists. Do you want to overwrite it?",
+                output.display()
+            )),
+            false,
+        )?
+    {
+        note_kept_file(&output.display());
+        return Ok(false);
+    }
+
+    // Create the parent directory if it does not exist
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).map_err(CodevaultError::io(format!(
+            "creating directory '{}'",
+            parent.display()
+        )))?;
+    }
+
+    // Keep every field, so the file can be read back without losing IDs, tags or timestamps
+    let content = serde_json::to_vec_pretty(snippets).map_err(CodevaultError::Serialize)?;
+    std::fs::write(output, content).map_err(CodevaultError::io(format!(
+        "writing to file '{}'",
+        output.display()
+    )))?;
+
+    // Confirm successful export
+    ctx.announce(&format!(
+        "\x1b[1;32m\nSuccessfully exported {} snippets to file '{}'.\x1b[0m",
+        snippets.len(),
+        output.display()
+    ));
+
+    Ok(true)
+}
+
+// Function to export snippets into a single Markdown file, each as a fenced code block.
+// Returns false when the file already exists and the user chose to keep it.
+fn export_markdown(
+    ctx: &Context,
+    snippets: &[Snippet],
+    export_dir: &Path,
+    force: bool,
+) -> Result<bool, CodevaultError> {
+    let filename = format!("{}/snippets.md", export_dir.display());
+
+    // Skip the file if it already exists and the user does not want to overwrite it
+    if !force
+        && Path::new(&filename).exists()
+        && !prompt_yes_no(
+            ctx,
+            &style::prompt(format!(
+                "\nThe file '{}' already exists. Do you want to overwrite it?",
+                filename
+            )),
+            false,
+        )?
+    {
+        note_kept_file(&filename);
+        return Ok(false);
+    }
+
+    std::fs::write(&filename, markdown_document(snippets)).map_err(CodevaultError::io(format!(
+        "writing to file '{}'",
+        filename
+    )))?;
+
+    // Confirm successful export
+    ctx.announce(&format!(
+        "\x1b[1;32m\nSuccessfully exported {} snippets to file '{}'.\x1b[0m",
+        snippets.len(),
+        filename
+    ));
+
+    Ok(true)
+}
+
+// Function to build a Markdown document with one section and fenced code block per snippet
+fn markdown_document(snippets: &[Snippet]) -> String {
+    let mut markdown = String::new();
+    for snippet in snippets {
+        // Heading with the snippet's ID and tags, followed by its description
+        markdown.push_str(&format!(
+            "## {}. {}\n\n",
+            snippet.id,
+            snippet.tags_display()
+        ));
+        if let Some(desc) = &snippet.description {
+            markdown.push_str(&format!("{}\n\n", desc));
+        }
+
+        // Use a fence longer than any backtick run inside the code so it can't close early
+        let longest_backticks = snippet
+            .code
+            .split(|c| c != '`')
+            .map(|run| run.len())
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_backticks.max(2) + 1);
+
+        // Use the file extension as the info string, plain text snippets get none
+        let info = match language_extension(snippet.language.as_deref()) {
+            "txt" => "",
+            extension => extension,
+        };
+
+        markdown.push_str(&format!("{}{}\n{}", fence, info, snippet.code));
+        if !snippet.code.ends_with('\n') {
+            markdown.push('\n');
+        }
+        markdown.push_str(&format!("{}\n\n", fence));
+    }
+
+    markdown.trim_end().to_string() + "\n"
+}
+
+// Function to build a standalone HTML page for the snippets, highlighted with inline styles
+// from the configured theme so it renders in a browser without any other file
+fn html_document(ctx: &Context, snippets: &[Snippet]) -> String {
+    let ps = syntax_set();
+    let theme = &theme_set().themes[&ctx.config.theme];
+
+    let title = match snippets {
+        [snippet] => format!("Snippet {}", snippet.id),
+        _ => "Codevault snippets".to_string(),
+    };
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\nbody {{ font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }}\npre {{ padding: 1rem; overflow-x: auto; border-radius: 6px; }}\n</style>\n</head>\n<body>\n",
+        html_escape(&title)
+    );
+
+    for snippet in snippets {
+        // Heading with the snippet's ID and tags, followed by its description, like the Markdown export
+        html.push_str(&format!(
+            "<section>\n<h2>{}. {}</h2>\n",
+            snippet.id,
+            html_escape(&snippet.tags_display())
+        ));
+        if let Some(desc) = &snippet.description {
+            html.push_str(&format!("<p>{}</p>\n", html_escape(desc)));
+        }
+
+        // Plain text snippets, and code syntect fails on, are still shown, just without colors
+        let syntax = snippet
+            .language
+            .as_deref()
+            .and_then(resolve_language)
+            .and_then(|syntax| ps.find_syntax_by_name(&syntax.name))
+            .unwrap_or(ps.find_syntax_plain_text());
+        let code = syntect::html::highlighted_html_for_string(&snippet.code, ps, syntax, theme)
+            .unwrap_or_else(|_| format!("<pre>{}</pre>\n", html_escape(&snippet.code)));
+        html.push_str(&code);
+        html.push_str("</section>\n");
+    }
+
+    html + "</body>\n</html>\n"
+}
+
+// Function to escape text for use in HTML content
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{test_snippet, DEFAULT_THEME};
+
+    #[test]
+    fn format_with_border_aligns_wide_characters() {
+        // A Chinese comment and an emoji each take two terminal cells per character
+        let ascii = strip_ansi_codes(&format_with_border("  // hello", 24));
+        let wide = strip_ansi_codes(&format_with_border("  // 你好世界 🚀", 24));
+
+        assert_eq!(ascii.width(), wide.width());
+        assert_eq!(wide.width(), 26);
+    }
+
+    #[test]
+    fn highlighted_lines_reset_colors_before_the_line_ending() {
+        let highlighted =
+            highlight_code_snippets("let x = 1;\nlet y = 2;\n", "rust", None, DEFAULT_THEME);
+
+        // Every line carries a background color and ends with a reset, not with a color still set
+        for line in highlighted.lines() {
+            assert!(line.contains("48;2;"));
+            assert!(line.ends_with("\x1b[0m"));
+        }
+        assert_eq!(strip_ansi_codes(&highlighted), "let x = 1;\nlet y = 2;\n");
+
+        // The escapes don't count towards the width of the bordered line
+        let first = highlighted.lines().next().unwrap();
+        assert_eq!(strip_ansi_codes(&format_with_border(first, 24)).width(), 26);
+    }
+
+    #[test]
+    fn block_comments_stay_highlighted_across_lines() {
+        let highlighted =
+            highlight_code_snippets("/* one\ntwo */\nlet x = 1;\n", "rust", None, DEFAULT_THEME);
+        let lines: Vec<&str> = highlighted.lines().collect();
+
+        // The second line is still inside the comment, so it gets the comment's color, not the code's
+        let color = |line: &str| line[..line.find('m').unwrap()].to_string();
+        assert_eq!(color(lines[0]), color(lines[1]));
+        assert_ne!(color(lines[1]), color(lines[2]));
+    }
+
+    #[test]
+    fn import_points_at_the_first_invalid_entry_unless_lenient() {
+        let ctx = &Context::default();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("codevault.json");
+        let file_path = path.to_str().unwrap();
+        let content = r#"[
+  {"tag": ["a"], "description": null, "code": "one", "timestamp": "2024-08-24T14:34:00+00:00", "language": "rs", "id": 9},
+  {"tag": ["b"], "description": null, "timestamp": "2024-08-24T14:34:00+00:00", "language": null, "id": 9}
+]"#;
+
+        let err = import_snippets(ctx, file_path, content, "test", false).unwrap_err();
+        assert!(err.to_string().contains("entry 2 at line 3, column"));
+        assert!(!path.exists());
+
+        let summary = import_snippets(ctx, file_path, content, "test", true).unwrap();
+        let snippets = read_snippets(ctx, file_path).unwrap();
+        assert_eq!(summary.imported, vec![1]);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(snippets[0].language.as_deref(), Some("Rust"));
+    }
+
+    #[test]
+    fn edit_by_tag_replaces_the_single_match() {
+        let ctx = &Context::default();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let file_path = file.path().to_str().unwrap();
+        let snippet = |id: u32, tag: &str, code: &str| Snippet {
+            language: Some("Rust".to_string()),
+            ..test_snippet(id, &[tag], code)
+        };
+        let snippets = [snippet(1, "solo", "one"), snippet(2, "other", "two")];
+        std::fs::write(file_path, serde_json::to_string(&snippets).unwrap()).unwrap();
+
+        let fields = EditFields {
+            language: Some("c".to_string()),
+            ..EditFields::default()
+        };
+        let outcome = edit_snippet(
+            ctx,
+            file_path,
+            &None,
+            &Some("solo".to_string()),
+            &fields,
+            false,
+            false,
+        )
+        .unwrap();
+        let snippets = read_snippets(ctx, file_path).unwrap();
+        assert!(matches!(outcome, EditOutcome::Saved));
+        assert_eq!(snippets.len(), 2);
+        let mut ids: Vec<u32> = snippets.iter().map(|s| s.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+        let edited = snippets.iter().find(|s| s.id == 1).unwrap();
+        assert_eq!(edited.language.as_deref(), Some("C"));
+    }
+
+    #[test]
+    fn load_config_fills_in_defaults_and_rejects_unknown_themes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        // A missing file and a partial file both get the defaults for what they leave out
+        assert_eq!(load_config(&path).unwrap(), Config::default());
+        std::fs::write(&path, "export_dir = \"exports\"\ncolor = false\n").unwrap();
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.theme, models::DEFAULT_THEME);
+        assert_eq!(config.export_dir, Some(PathBuf::from("exports")));
+        assert!(!config.color);
+
+        std::fs::write(&path, "theme = \"no such theme\"\n").unwrap();
+        let err = load_config(&path).unwrap_err();
+        assert!(err.to_string().contains("unknown theme 'no such theme'"));
+    }
+
+    #[test]
+    fn templates_are_saved_in_the_config_file_keeping_its_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "# my settings\ncolor = false # plain output\n").unwrap();
+
+        let template = Template {
+            tag: Some("react, frontend".to_string()),
+            language: Some("JavaScript".to_string()),
+            ..Template::default()
+        };
+        assert!(!add_template(&path, "react", &template).unwrap());
+        assert!(add_template(&path, "react", &template).unwrap());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my settings\ncolor = false # plain output\n"));
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.templates["react"], template);
+
+        remove_template(&path, "react").unwrap();
+        assert!(matches!(
+            remove_template(&path, "react"),
+            Err(CodevaultError::TemplateNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn render_snippet_shows_only_the_chosen_fields_in_order() {
+        let snippet = Snippet {
+            description: Some("greeting".to_string()),
+            ..test_snippet(7, &["demo"], "hello\n")
+        };
+        let display = DisplayOptions {
+            fields: vec![SnippetField::Description, SnippetField::Id],
+            ..DisplayOptions::default()
+        };
+
+        let rendered = strip_ansi_codes(&render_snippet(&snippet, &display));
+        let description = rendered.find("Description: greeting").unwrap();
+        let id = rendered.find("ID: 7").unwrap();
+
+        assert!(description < id);
+        assert!(!rendered.contains("Tag:"));
+        assert!(!rendered.contains("hello"));
+
+        // Snippets stored without a language are shown as plain text
+        let summary = strip_ansi_codes(&render_summary(&snippet, None, DEFAULT_THEME));
+        assert!(summary.contains("Language: plain text"));
+    }
+
+    #[test]
+    fn render_grep_shows_matching_lines_with_context() {
+        let snippet = test_snippet(4, &["demo"], "a\nmatch one\nb\nc\nd\ne\nmatch two\n");
+        let pattern = Regex::new("match").unwrap();
+
+        let rendered = strip_ansi_codes(
+            &render_grep(&snippet, &pattern, 1, &DisplayOptions::default()).unwrap(),
+        );
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines[..7],
+            [
+                "ID 4 demo",
+                "1- a",
+                "2: match one",
+                "3- b",
+                "--",
+                "6- e",
+                "7: match two"
+            ]
+        );
+
+        // Snippets without a matching line are left out
+        let pattern = Regex::new("missing").unwrap();
+        assert!(render_grep(&snippet, &pattern, 1, &DisplayOptions::default()).is_none());
+    }
+
+    #[test]
+    fn renumber_duplicate_ids_keeps_the_first_snippet_of_each_id() {
+        let mut snippets = vec![
+            test_snippet(2, &[], "a"),
+            test_snippet(5, &[], "b"),
+            test_snippet(2, &[], "c"),
+            test_snippet(2, &[], "d"),
+        ];
+
+        assert_eq!(duplicate_ids(&snippets), vec![2]);
+        assert_eq!(renumber_duplicate_ids(&mut snippets), vec![(2, 6), (2, 7)]);
+        let ids: Vec<u32> = snippets.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![2, 5, 6, 7]);
+        assert!(duplicate_ids(&snippets).is_empty());
+    }
+
+    #[test]
+    fn loading_leaves_duplicate_ids_to_the_commands_that_save() {
+        let ctx = &Context::default();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let file_path = file.path().to_str().unwrap();
+        let content =
+            serde_json::to_string(&[test_snippet(1, &[], "a"), test_snippet(1, &[], "b")]).unwrap();
+        std::fs::write(file_path, &content).unwrap();
+
+        let snippets = load_snippets(ctx, file_path).unwrap();
+        assert_eq!(duplicate_ids(&snippets), vec![1]);
+        assert_eq!(std::fs::read_to_string(file_path).unwrap(), content);
+
+        set_pinned(ctx, file_path, 1, true).unwrap();
+        let ids: Vec<u32> = read_snippets(ctx, file_path)
+            .unwrap()
+            .iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn append_only_capture_handles_any_layout_of_the_array() {
+        let ctx = &Context::default();
+        let record = |id: u32, code: &str| test_snippet(id, &["rust"], code);
+        let existing = serde_json::to_string_pretty(&[record(7, "{\n    \"id\": 99,\n}")])
+            .unwrap()
+            .replace('\n', "\r\n");
+
+        for (content, next_id) in [
+            ("", 1),
+            (" \n", 1),
+            ("[]", 1),
+            ("[\r\n]\r\n", 1),
+            (&existing, 8),
+        ] {
+            let file = tempfile::NamedTempFile::new().unwrap();
+            let file_path = file.path().to_str().unwrap();
+            std::fs::write(file_path, content).unwrap();
+
+            let id = generate_append_id(ctx, file_path).unwrap();
+            assert_eq!(id, next_id);
+            assert!(save_snippet(ctx, record(id, "fn main() {}"), file_path, false, true).unwrap());
+            let ids: Vec<u32> = read_snippets(ctx, file_path)
+                .unwrap()
+                .iter()
+                .map(|s| s.id)
+                .collect();
+            assert_eq!(ids.last(), Some(&next_id));
+            assert_eq!(generate_append_id(ctx, file_path).unwrap(), next_id + 1);
+        }
+
+        // Identical code is still declined unless duplicates are allowed
+        let quiet = &Context::new(Config::default(), true, false, false);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let file_path = file.path().to_str().unwrap();
+        std::fs::write(file_path, &existing).unwrap();
+        let duplicate = record(8, "{\n    \"id\": 99,\n}");
+        assert!(!save_snippet(quiet, duplicate.clone(), file_path, false, true).unwrap());
+        assert_eq!(std::fs::read_to_string(file_path).unwrap(), existing);
+        assert!(save_snippet(quiet, duplicate, file_path, true, true).unwrap());
+    }
+
+    #[test]
+    fn summary_previews_the_first_code_line_on_a_single_line() {
+        let code = format!("\n  let 你好 = \"{}\";\nsecond\n", "x".repeat(200));
+        let snippet = Snippet {
+            language: Some("rust".to_string()),
+            ..test_snippet(3, &[], &code)
+        };
+
+        let summary = strip_ansi_codes(&render_summary(&snippet, None, DEFAULT_THEME));
+        let code_line = summary.lines().find(|line| line.contains("Code:")).unwrap();
+        assert!(code_line.contains("Code: let 你好 = \"x"));
+        assert!(code_line.contains("…"));
+        assert!(!summary.contains("second"));
+        assert!(summary
+            .lines()
+            .all(|line| line.width() <= DEFAULT_BOX_WIDTH));
+        assert_eq!(truncate_to_width("你好世界", 5), "你好…");
+    }
+
+    #[test]
+    fn diff_marks_removed_and_added_lines() {
+        let snippet = |id: u32, code: &str| Snippet {
+            language: Some("rust".to_string()),
+            ..test_snippet(id, &["rust"], code)
+        };
+        let old = snippet(3, "fn main() {\n    old();\n}\n");
+        let new = snippet(7, "fn main() {\n    new();\n}\n");
+
+        let lines: Vec<String> = diff_sections(&old, &new)
+            .concat()
+            .iter()
+            .map(|line| strip_ansi_codes(line))
+            .collect();
+        assert_eq!(lines[0], "  --- ID 3 (rust)");
+        assert_eq!(lines[1], "  +++ ID 7 (rust)");
+        assert!(lines.contains(&"  -    old();".to_string()));
+        assert!(lines.contains(&"  +    new();".to_string()));
+        assert!(lines.contains(&"   fn main() {".to_string()));
+        assert!(diff_sections(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn wrap_ansi_line_counts_wide_characters_as_two_cells() {
+        let segments = wrap_ansi_line("你好世界🚀", 4, 4);
+
+        assert_eq!(segments, vec!["你好", "世界", "🚀"]);
+    }
+}
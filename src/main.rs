@@ -1,19 +1,38 @@
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufWriter, Read, Write};
-use std::path::PathBuf;
-
-use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, ThemeSet};
-use syntect::parsing::SyntaxSet;
-use syntect::util::LinesWithEndings;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 use chrono::Local;
-use clap::{Parser, Subcommand};
-
-mod models;
-use models::strip_ansi_codes;
-use models::vault_styling;
-use models::Snippet;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use clap_complete::{generate, Shell};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use rayon::prelude::*;
+
+use codevault::error::CodevaultError;
+use codevault::models::format_timestamp;
+use codevault::models::{compile_pattern, Snippet, SortKey, TagCase, Template, ViewFilter};
+use codevault::models::{
+    init_color, init_compact, init_relative_time, paint, parse_ids, parse_tags,
+};
+use codevault::models::{no_color_env, Config, Context};
+use codevault::models::{spaced, syntax_set, vault_styling};
+use codevault::models::{
+    DisplayOptions, EditFields, EditOutcome, ExportFormat, ExportOptions, LineEnding, SnippetField,
+};
+use codevault::style;
+use codevault::{
+    add_template, append_to_snippet, canonical_language, capture_snippet, code_only_separator,
+    copy_code, default_config_path, delete_all_snippets, delete_snippet, detect_language,
+    detect_language_from_path, diff_snippets, duplicate_snippet, edit_snippet, export_snippets,
+    find_template, generate_append_id, generate_unique_id, highlight_code_snippets,
+    import_snippets, list_trash, load_config, matching_snippet_ids, merge_snippets,
+    normalize_snippet_tags, open_in_editor, page_output, pick_snippet, print_loc,
+    print_snippet_summary, print_stats, print_tag_tree, print_tags, print_templates,
+    print_used_languages, prompt, recent_snippets, remove_template, render_code_only, render_grep,
+    render_snippet, render_summary, repair_snippets, restore_version, retag_snippets, save_snippet,
+    set_language, set_pinned, snippet_history, snippets_with_unknown_language, undo_delete,
+    validate_language, validate_theme, view_in_editor, view_snippets, watch_clipboard,
+};
 
 // data file stored in data dir
 const DATA_FILE: &str = "data/codevault.json";
@@ -56,6 +75,72 @@ pub struct Cli {
     // Optional tags for categorizing the code snippets, accessible via short (-t) or long (--tag) flag
     #[arg(short, long, help = "Apply relevant tags to categorize the snippets")]
     tag: Option<String>,
+
+    // Disable colored output, also honored through the NO_COLOR environment variable
+    #[arg(long = "no-color", global = true, help = "Disable colored output")]
+    no_color: bool,
+
+    // Leave out headers, success banners and prompts, printing only the requested data and errors
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        help = "Only print requested data and errors, never prompt"
+    )]
+    quiet: bool,
+
+    // Answer yes to every confirmation, so scripts never wait on a prompt
+    #[arg(
+        short = 'y',
+        long = "yes",
+        global = true,
+        help = "Answer yes to every confirmation, e.g. when deleting or overwriting"
+    )]
+    yes: bool,
+
+    // Leave out the blank lines around headers and boxes, to fit more on the screen
+    #[arg(
+        long = "compact",
+        global = true,
+        help = "Drop the blank lines around headers, messages and snippet boxes"
+    )]
+    compact: bool,
+
+    // Show when snippets were created and modified relative to now, e.g. "3 days ago"
+    #[arg(
+        long = "relative",
+        global = true,
+        help = "Show timestamps relative to now, e.g. \"3 days ago\", instead of the date and time"
+    )]
+    relative: bool,
+
+    // Encrypt the snippets file with a passphrase, also read from the CODEVAULT_PASSPHRASE environment variable
+    #[arg(
+        long = "encrypt",
+        global = true,
+        help = "Encrypt the snippets file with a passphrase when it is saved"
+    )]
+    encrypt: bool,
+
+    // Snippets file to use instead of the default one, also read from the CODEVAULT_DATA environment variable
+    #[arg(
+        long = "data",
+        global = true,
+        env = "CODEVAULT_DATA",
+        value_name = "PATH",
+        help = "Use the snippets file at this path [default: data_path from the config file or data/codevault.json]"
+    )]
+    data: Option<String>,
+
+    // Config file to read instead of the default one, also read from the CODEVAULT_CONFIG environment variable
+    #[arg(
+        long = "config",
+        global = true,
+        env = "CODEVAULT_CONFIG",
+        value_name = "PATH",
+        help = "Read the config file at this path [default: ~/.config/codevault/config.toml]"
+    )]
+    config: Option<PathBuf>,
 }
 
 // Define a set of subcommands for the CLI using the Commands enum
@@ -68,25 +153,137 @@ pub enum Commands {
         #[arg(
             short = 'd',
             long = "description",
-            help = "Add a description to the provided code snippet"
+            help = "Add a description to the provided code snippet (asked for in a terminal when omitted)"
         )]
-        description: String,
+        description: Option<String>,
 
         // Argument to specify the programming language for syntax highlighting, accessible with -l or --language
         #[arg(
             short = 'l',
             long = "language",
-            help = "Select a programming language for syntax highlighting"
+            help = "Select a programming language for syntax highlighting (detected from the code when omitted)"
         )]
-        language: String,
+        language: Option<String>,
 
         // Argument to apply relevant tags for categorizing the snippet, accessible with -t or --tag
         #[arg(
             short = 't',
             long = "tag",
-            help = "Apply relevant tags to categorize the snippets"
+            help = "Apply relevant tags to categorize the snippets, comma-separated (asked for in a terminal when omitted)"
+        )]
+        tag: Option<String>,
+
+        // Flag to write the code in $VISUAL/$EDITOR instead of the stdin prompt, accessible with -e or --editor
+        #[arg(
+            short = 'e',
+            long = "editor",
+            help = "Write the code snippet in $VISUAL/$EDITOR instead of the terminal prompt"
+        )]
+        editor: bool,
+
+        // Flag to type or paste the code into the terminal instead of the editor, accessible with --stdin-code
+        #[arg(
+            long = "stdin-code",
+            conflicts_with_all = ["editor", "from_file", "watch"],
+            help = "Type or paste the code into the terminal until 'Ctrl+D', instead of writing it in the editor"
+        )]
+        stdin_code: bool,
+
+        // Flag to save the snippet even if the same code is already captured, accessible with --allow-duplicate
+        #[arg(
+            long = "allow-duplicate",
+            help = "Save the snippet without checking for identical code in the collection"
+        )]
+        allow_duplicate: bool,
+
+        // Flag to add the snippet to the end of the snippets file without rebuilding the collection, accessible with --append-only
+        #[arg(
+            long = "append-only",
+            help = "Add the snippet to the end of the snippets file without rebuilding the collection"
+        )]
+        append_only: bool,
+
+        // Argument to fill in the options left out from a template saved with `template add`, accessible with -T or --template
+        #[arg(
+            short = 'T',
+            long = "template",
+            value_name = "NAME",
+            help = "Fill in the tag, description and language left out from a saved template"
+        )]
+        template: Option<String>,
+
+        // Argument to show the snippet in its own theme, accessible with --theme
+        #[arg(
+            long = "theme",
+            conflicts_with = "watch",
+            help = "Highlight the snippet in this theme instead of the configured one, e.g. for diffs or logs"
+        )]
+        theme: Option<String>,
+
+        // Argument to record where the code came from, such as a file path or URL, accessible with -s or --source
+        #[arg(
+            short = 's',
+            long = "source",
+            help = "Record where the code snippet came from, e.g. a file path or URL"
+        )]
+        source: Option<String>,
+
+        // Argument to read the code from a file instead of the terminal, accessible with --from-file
+        #[arg(
+            long = "from-file",
+            conflicts_with = "editor",
+            help = "Read the code snippet from a file, detecting the language from its extension"
+        )]
+        from_file: Option<PathBuf>,
+
+        // Flag to add the code to the end of an existing snippet instead, accessible with -a or --append
+        #[arg(
+            short = 'a',
+            long = "append",
+            requires = "id",
+            conflicts_with_all = ["tag", "description", "language", "source", "allow_duplicate", "template", "theme"],
+            help = "Append the code to the end of the snippet given with --id instead of capturing a new one"
+        )]
+        append: bool,
+
+        // Argument to specify the snippet to append to, accessible with -i or --id
+        #[arg(
+            short = 'i',
+            long = "id",
+            requires = "append",
+            help = "The unique ID of the snippet to append to"
+        )]
+        id: Option<u32>,
+
+        // Flag to keep capturing whatever is copied to the clipboard until Ctrl+C, accessible with -w or --watch
+        #[arg(
+            short = 'w',
+            long = "watch",
+            conflicts_with_all = ["editor", "from_file", "append", "source"],
+            help = "Watch the clipboard and capture each new text copied to it, until 'Ctrl+C'"
+        )]
+        watch: bool,
+    },
+
+    // Subcommand to print a shell completion script to stdout
+    #[command(
+        about = "Generate a shell completion script for bash, zsh, fish, elvish or powershell"
+    )]
+    Completions {
+        // Argument to choose the shell to generate the completion script for
+        #[arg(value_enum, help = "The shell to generate the completion script for")]
+        shell: Shell,
+    },
+
+    // Subcommand to locate the config file and show the settings in effect
+    #[command(about = "Show where the config file is read from, or the settings in effect")]
+    Config {
+        // Flag to print the settings after the command-line flags and environment variables are applied
+        #[arg(
+            long = "show",
+            help = "Print the settings in effect as TOML, with flags and environment variables applied"
         )]
-        tag: String,
+        show: bool,
     },
 
     // Subcommand to show the code of a specified snippet using its ID
@@ -99,11 +296,28 @@ pub enum Commands {
             help = "Unique ID automatically assigned for identification of the snippets"
         )]
         id: Option<u32>,
+
+        // Flag to write only the stored code, without header or colors, accessible with --raw
+        #[arg(
+            long = "raw",
+            help = "Output only the exact stored code, for piping or redirecting to a file"
+        )]
+        raw: bool,
+
+        // Argument to highlight the code as another language this time only, accessible with -l or --language
+        #[arg(
+            short = 'l',
+            long = "language",
+            conflicts_with = "raw",
+            help = "Highlight the code as this language instead of the stored one, without changing the snippet"
+        )]
+        language: Option<String>,
     },
 
-    // Subcommand to remove one or more code snippets by specifying their IDs
+    // Subcommand to remove one or more code snippets by specifying their IDs, tags, or languages
     #[command(
-        about = "Remove one or more code snippets by specifying their IDs, separated by commas"
+        about = "Remove code snippets by their IDs (separated by commas), tags, or languages",
+        group(ArgGroup::new("selector").required(true).multiple(true).args(["id", "tag", "language", "all"]))
     )]
     Delete {
         // Argument to specify the unique ID(s) of the snippets to delete, accessible with -i or --id
@@ -113,6 +327,82 @@ pub enum Commands {
             help = "Unique ID automatically assigned for identification of the snippets"
         )]
         id: Option<String>,
+
+        // Argument to delete every snippet matching the tag(s), accessible with -t or --tag
+        #[arg(
+            short = 't',
+            long = "tag",
+            help = "Delete all snippets matching the tag(s), separated by commas"
+        )]
+        tag: Option<String>,
+
+        // Argument to delete every snippet in the language(s), accessible with -l or --language
+        #[arg(
+            short = 'l',
+            long = "language",
+            help = "Delete all snippets in the language(s), separated by commas"
+        )]
+        language: Option<String>,
+
+        // Flag to empty the whole collection, confirmed by typing DELETE, accessible with --all
+        #[arg(
+            long = "all",
+            conflicts_with_all = ["id", "tag", "language"],
+            help = "Delete every snippet in the collection, after typing DELETE to confirm"
+        )]
+        all: bool,
+
+        // Flag to match whole tags and language names only, accessible with -x or --exact
+        #[arg(
+            short = 'x',
+            long = "exact",
+            help = "Match whole tags and language names, not nested tags or parts of names"
+        )]
+        exact: bool,
+
+        // Flag to list the snippets that would be deleted without removing them
+        #[arg(
+            long = "dry-run",
+            help = "Show the snippets that would be deleted without deleting them"
+        )]
+        dry_run: bool,
+    },
+
+    // Subcommand to compare the code of two snippets
+    #[command(about = "Show what changed between the code of two snippets as a unified diff")]
+    Diff {
+        // Argument for the two snippets to compare, accessible with -i or --id, e.g. -i 3 -i 7
+        #[arg(
+            short = 'i',
+            long = "id",
+            required = true,
+            num_args = 1..=2,
+            help = "The IDs of the snippets to compare, the first one as the old code, e.g. -i 3 -i 7"
+        )]
+        ids: Vec<u32>,
+    },
+
+    // Subcommand to copy a snippet to a new ID, so it can be changed without touching the original
+    #[command(
+        alias = "clone",
+        about = "Copy a snippet to a new ID in your collection"
+    )]
+    Duplicate {
+        // Argument to specify the unique ID of the snippet to duplicate, accessible with -i or --id
+        #[arg(
+            short = 'i',
+            long = "id",
+            help = "The unique ID of the snippet to duplicate"
+        )]
+        id: u32,
+
+        // Flag to edit the new copy right away, accessible with -e or --edit
+        #[arg(
+            short = 'e',
+            long = "edit",
+            help = "Edit the new copy right after it is saved"
+        )]
+        edit: bool,
     },
 
     // Subcommand to modify an existing code snippet in the collection
@@ -130,13 +420,43 @@ pub enum Commands {
         #[arg(short, long, help = "The tag of the snippet to edit")]
         tag: Option<String>,
 
-        // Argument to specify the programming language for syntax highlighting, accessible with -l or --language
+        // Argument to set a new language without the interactive prompts, accessible with -l or --language
         #[arg(
             short,
             long,
-            help = "Select a programming language to syntax highlight the code snippet"
+            help = "Set the language of the snippet and save without going through the prompts"
         )]
         language: Option<String>,
+
+        // Argument to set a new description without the interactive prompts, accessible with -d or --description
+        #[arg(
+            short,
+            long,
+            help = "Set the description of the snippet and save without going through the prompts"
+        )]
+        description: Option<String>,
+
+        // Argument to show the snippet in its own theme, accessible with --theme
+        #[arg(
+            long = "theme",
+            help = "Highlight the snippet in this theme instead of the configured one, an empty name goes back to it"
+        )]
+        theme: Option<String>,
+
+        // Flag to edit the code in $VISUAL/$EDITOR instead of the stdin prompt, accessible with -e or --editor
+        #[arg(
+            short = 'e',
+            long = "editor",
+            help = "Edit the code snippet in $VISUAL/$EDITOR instead of the terminal prompt"
+        )]
+        editor: bool,
+
+        // Flag to preview the edited snippet without saving the changes
+        #[arg(
+            long = "dry-run",
+            help = "Show the edited snippet without saving the changes"
+        )]
+        dry_run: bool,
     },
 
     // Subcommand to export code snippets by specifying IDs, tags, or languages
@@ -144,13 +464,13 @@ pub enum Commands {
         about = "Export code snippet or a batch by specifying their IDs, tags, or languages."
     )]
     Export {
-        // Argument to specify the unique ID of the snippet to export, accessible with -i or --id
+        // Argument to specify the unique ID(s) of the snippets to export, accessible with -i or --id
         #[arg(
             short = 'i',
             long = "id",
-            help = "Export code snippet by its unique ID"
+            help = "Export code snippets by their unique IDs (comma-separated)"
         )]
-        id: Option<u32>,
+        id: Option<String>,
 
         // Argument to export snippets based on the specified programming language, accessible with -l or --language
         #[arg(
@@ -168,18 +488,316 @@ pub enum Commands {
         )]
         tag: Option<String>,
 
+        // Flag to match whole tags and language names only, accessible with -x or --exact
+        #[arg(
+            short = 'x',
+            long = "exact",
+            help = "Match whole tags and language names, not nested tags or parts of names"
+        )]
+        exact: bool,
+
         // Argument to specify the directory where the snippet should be exported, accessible with -p or --path
         #[arg(
             short = 'p',
             long = "path",
-            help = "Specify the directory where the snippet should be exported"
+            help = "Specify the directory where the snippet should be exported [default: $CODEVAULT_EXPORT_DIR or snippet_exports]"
         )]
         path: Option<PathBuf>,
+
+        // Argument to choose between one file per snippet, a single Markdown file or HTML pages, accessible with -f or --format
+        #[arg(
+            short = 'f',
+            long = "format",
+            value_enum,
+            default_value_t = ExportFormat::Files,
+            help = "Export one file per snippet, a single Markdown file, or one highlighted HTML page per snippet"
+        )]
+        format: ExportFormat,
+
+        // Flag to overwrite existing export files without asking, accessible with --force
+        #[arg(
+            long = "force",
+            help = "Overwrite existing export files without asking"
+        )]
+        force: bool,
+
+        // Argument to write the snippets into one JSON file instead, accessible with -o or --output
+        #[arg(
+            short = 'o',
+            long = "output",
+            conflicts_with_all = ["path", "format"],
+            help = "Export the snippets as a single JSON file, keeping IDs, tags and timestamps"
+        )]
+        output: Option<PathBuf>,
+
+        // Flag to save every snippet with the .txt extension whatever its language, accessible with --plain
+        #[arg(
+            long = "plain",
+            conflicts_with_all = ["output", "format"],
+            help = "Export every snippet as a .txt file, ignoring its language"
+        )]
+        plain: bool,
+
+        // Flag to write the snippet to stdout instead of a file, accessible with --stdout
+        #[arg(
+            long = "stdout",
+            conflicts_with_all = ["path", "output", "force", "plain"],
+            help = "Write the code of a single snippet, or the Markdown of several with --format markdown, to stdout"
+        )]
+        stdout: bool,
+
+        // Argument to choose the line endings of the exported code, accessible with --line-endings
+        #[arg(
+            long = "line-endings",
+            value_enum,
+            default_value_t = LineEnding::Lf,
+            conflicts_with = "output",
+            help = "Line endings of the exported code, 'keep' writes it exactly as stored"
+        )]
+        line_endings: LineEnding,
+
+        // Flag to report every exported file instead of a progress bar, accessible with -v or --verbose
+        #[arg(
+            short = 'v',
+            long = "verbose",
+            help = "Print a line for every exported file instead of a progress bar"
+        )]
+        verbose: bool,
+
+        // Flag to only export what changed since the previous export made with it, accessible with --since-last
+        #[arg(
+            long = "since-last",
+            help = "Only export the snippets added or modified since the last export made with --since-last"
+        )]
+        since_last: bool,
+    },
+
+    // Subcommand to list the previous versions of a snippet's code
+    #[command(about = "List the previous versions of a snippet's code, newest first")]
+    History {
+        // Argument to specify the unique ID of the snippet, accessible with -i or --id
+        #[arg(short = 'i', long = "id", help = "The unique ID of the snippet")]
+        id: u32,
+    },
+
+    // Subcommand to add the snippets of a JSON file, e.g. one written by `export --output`
+    #[command(
+        about = "Import snippets from a JSON file or stdin, as an array or a single snippet"
+    )]
+    Import {
+        // Argument for the JSON file to read, stdin is read when it is left out or '-'
+        #[arg(
+            value_name = "FILE",
+            help = "The JSON file to import, read from stdin when omitted or '-'"
+        )]
+        file: Option<PathBuf>,
+
+        // Flag to import the valid entries and skip the others, accessible with --lenient
+        #[arg(
+            long = "lenient",
+            help = "Import the valid entries and skip the invalid ones instead of importing nothing"
+        )]
+        lenient: bool,
     },
 
     // Subcommand to list all programming languages supported for syntax highlighting
     #[command(about = "List of all programming languages supported for syntax highlighting")]
-    Languages,
+    Languages {
+        // Flag to list only the languages of the stored snippets, accessible with -u or --used
+        #[arg(
+            short = 'u',
+            long = "used",
+            help = "List only the languages used by your snippets, with the number of snippets for each"
+        )]
+        used: bool,
+
+        // Argument to list only the languages whose name contains this text, ignoring case
+        #[arg(
+            conflicts_with = "used",
+            help = "List only the languages whose name contains this text"
+        )]
+        filter: Option<String>,
+
+        // Flag to print only how many languages are listed, accessible with -c or --count
+        #[arg(
+            short = 'c',
+            long = "count",
+            conflicts_with = "used",
+            help = "Print only the number of languages instead of listing them"
+        )]
+        count: bool,
+    },
+
+    // Subcommand to count the lines of code in each language
+    #[command(
+        about = "Report the lines of code in each language across the collection, most first"
+    )]
+    Loc,
+
+    // Subcommand to fold the code of one snippet into another
+    #[command(about = "Append the code of one snippet to another, optionally removing the source")]
+    Merge {
+        // Argument for the snippet that receives the code, accessible with --into
+        #[arg(long = "into", help = "The ID of the snippet the code is appended to")]
+        into: u32,
+
+        // Argument for the snippet whose code is appended, accessible with --from
+        #[arg(long = "from", help = "The ID of the snippet whose code is appended")]
+        from: u32,
+
+        // Flag to delete the source snippet after merging, accessible with --remove-source
+        #[arg(
+            long = "remove-source",
+            help = "Delete the --from snippet after merging, it can be restored with undo"
+        )]
+        remove_source: bool,
+    },
+
+    // Subcommand to rewrite every stored tag in one case, so "Rust", "rust" and "RUST" become one tag
+    #[command(about = "Rewrite all tags in one case, merging tags that only differ in case")]
+    NormalizeTags {
+        // Argument to choose the case tags are rewritten in, accessible with --case
+        #[arg(
+            long = "case",
+            value_enum,
+            default_value_t = TagCase::Lower,
+            help = "The case every tag is rewritten in"
+        )]
+        case: TagCase,
+    },
+
+    // Subcommand to read a snippet in the user's editor, without saving anything back
+    #[command(
+        about = "Open a snippet read-only in $VISUAL or $EDITOR, highlighted by its language"
+    )]
+    Open {
+        // Argument to specify the unique ID of the snippet to open, accessible with -i or --id
+        #[arg(
+            short = 'i',
+            long = "id",
+            help = "The unique ID of the snippet to open (picked from a list in a terminal when omitted)"
+        )]
+        id: Option<u32>,
+    },
+
+    // Subcommand to pin a snippet so it is listed first
+    #[command(about = "Pin a snippet so it is listed first in your collection")]
+    Pin {
+        // Argument to specify the unique ID of the snippet to pin, accessible with -i or --id
+        #[arg(short = 'i', long = "id", help = "The unique ID of the snippet to pin")]
+        id: u32,
+    },
+
+    // Subcommand to show the most recently captured snippets
+    #[command(about = "Show the most recently captured snippets")]
+    Recent {
+        // Argument to choose how many snippets to show, accessible with -c or --count
+        #[arg(
+            short = 'c',
+            long = "count",
+            default_value_t = 5,
+            help = "Number of recent snippets to show"
+        )]
+        count: usize,
+    },
+
+    // Subcommand to fix the snippets file, e.g. after it was edited by hand or merged
+    #[command(about = "Renumber snippets that share an ID with another snippet")]
+    Repair,
+
+    // Subcommand to roll a snippet's code back to a previous version
+    #[command(
+        about = "Bring back a previous version of a snippet's code",
+        disable_version_flag = true
+    )]
+    Restore {
+        // Argument to specify the unique ID of the snippet, accessible with -i or --id
+        #[arg(short = 'i', long = "id", help = "The unique ID of the snippet")]
+        id: u32,
+
+        // Argument to choose the version as numbered by the history command, accessible with --version
+        #[arg(
+            long = "version",
+            value_name = "N",
+            help = "The version to restore, 1 is the newest as listed by 'codevault history'"
+        )]
+        version: usize,
+    },
+
+    // Subcommand to rename a tag across the whole collection
+    #[command(about = "Rename a tag on every snippet that has it")]
+    Retag {
+        // Argument for the tag to replace, accessible with --from
+        #[arg(long = "from", help = "The tag to replace")]
+        from: String,
+
+        // Argument for the tag to use instead, accessible with --to
+        #[arg(long = "to", help = "The new tag")]
+        to: String,
+    },
+
+    // Subcommand to print an overview of the snippets collection
+    #[command(about = "Show statistics about your snippets collection")]
+    Stats,
+
+    // Subcommand to list the tags used in the collection
+    #[command(about = "List the tags in your collection with the number of snippets for each")]
+    Tags,
+
+    // Subcommand to inspect the snippets that can be restored
+    #[command(about = "Inspect recently deleted snippets that can be restored")]
+    Trash {
+        // Flag to list the deleted snippets in the trash, accessible with --list
+        #[arg(
+            long = "list",
+            required = true,
+            help = "List the deleted snippets, most recent first"
+        )]
+        list: bool,
+    },
+
+    // Subcommand to manage the capture templates kept in the config file
+    #[command(
+        about = "Manage the templates of tags, description and language used by 'capture --template'"
+    )]
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+
+    // Subcommand to show the nested tags, such as "web/frontend/react", as a tree
+    #[command(
+        about = "Show the tag hierarchy as a tree, tags nest by their '/' separated parts",
+        long_about = "Show the tag hierarchy as a tree with the number of snippets at or below each tag. Tags nest by their '/' separated parts, so 'web/frontend/react' is shown under 'frontend' under 'web'."
+    )]
+    Tree,
+
+    // Subcommand to restore the most recently deleted snippets
+    #[command(about = "Restore the most recently deleted snippets")]
+    Undo,
+
+    // Subcommand to remove the pin from a snippet
+    #[command(about = "Unpin a previously pinned snippet")]
+    Unpin {
+        // Argument to specify the unique ID of the snippet to unpin, accessible with -i or --id
+        #[arg(
+            short = 'i',
+            long = "id",
+            help = "The unique ID of the snippet to unpin"
+        )]
+        id: u32,
+    },
+
+    // Subcommand to check that every stored language can still be highlighted
+    #[command(about = "Report snippets whose language is not supported for highlighting")]
+    Verify {
+        // Flag to choose a new language for each reported snippet, accessible with --fix
+        #[arg(
+            long = "fix",
+            help = "Ask for a new language for each reported snippet, leaving it blank clears the language"
+        )]
+        fix: bool,
+    },
 
     // Subcommand to display the code of a specified snippet or all captured snippets if none is specified
     #[command(
@@ -198,6 +816,14 @@ pub enum Commands {
         #[arg(short, long, help = "Search for snippets by keyword (comma-separated)")]
         keyword: Option<String>,
 
+        // Argument to rank snippets by a fuzzy match of tags, description and code, accessible with -z or --fuzzy
+        #[arg(
+            short = 'z',
+            long = "fuzzy",
+            help = "Fuzzy search tags, descriptions and code, best matches first"
+        )]
+        fuzzy: Option<String>,
+
         // Argument to search for snippets by programming language, accessible with -l or --language
         #[arg(
             short,
@@ -214,20 +840,238 @@ pub enum Commands {
         )]
         summary: bool,
 
+        // Argument to choose which lines of the snippet box are shown and in what order, accessible with --fields
+        #[arg(
+            long = "fields",
+            value_enum,
+            value_delimiter = ',',
+            conflicts_with = "summary",
+            help = "Show only these lines of each snippet, in this order (comma-separated)"
+        )]
+        fields: Vec<SnippetField>,
+
         // Argument to search for snippets by tag, accessible with -t or --tag
         #[arg(short, long, help = "Search for snippets by tag (comma-separated)")]
         tag: Option<String>,
-    },
-}
-
 
-fn main() -> Result<(), String> {
+        // Argument to choose the order snippets are listed in, accessible with --sort
+        #[arg(
+            long = "sort",
+            value_enum,
+            default_value_t = SortKey::Id,
+            help = "Order the snippets by the given field"
+        )]
+        sort: SortKey,
+
+        // Flag to reverse the chosen order, accessible with -r or --reverse
+        #[arg(
+            short = 'r',
+            long = "reverse",
+            help = "Reverse the order of the snippets"
+        )]
+        reverse: bool,
+
+        // Flag to prefix each code line with its line number, accessible with -n or --line-numbers
+        #[arg(
+            short = 'n',
+            long = "line-numbers",
+            help = "Show line numbers next to the code"
+        )]
+        line_numbers: bool,
+
+        // Flag to let long code lines run past the box instead of wrapping them, accessible with --no-wrap
+        #[arg(
+            long = "no-wrap",
+            help = "Don't wrap long code lines to the terminal width"
+        )]
+        no_wrap: bool,
+
+        // Argument to choose how many columns a tab in the code takes, accessible with --tab-width
+        #[arg(
+            long = "tab-width",
+            value_name = "N",
+            default_value_t = 4,
+            help = "Show tabs in the code as spaces up to the next multiple of N columns"
+        )]
+        tab_width: usize,
+
+        // Flag to match whole tags and language names only, accessible with -x or --exact
+        #[arg(
+            short = 'x',
+            long = "exact",
+            help = "Match whole tags and language names, not nested tags or parts of names"
+        )]
+        exact: bool,
+
+        // Flag to show Markdown snippets formatted instead of highlighted, accessible with --render
+        #[arg(
+            long = "render",
+            help = "Show Markdown snippets formatted, with headings, emphasis and lists, instead of their source"
+        )]
+        render: bool,
+
+        // Flag to print only the highlighted code of each snippet, separated by a thin rule, accessible with --code-only
+        #[arg(
+            long = "code-only",
+            conflicts_with_all = ["summary", "fields"],
+            help = "Print only the code of each snippet, without boxes or details, for reading through them"
+        )]
+        code_only: bool,
+
+        // Argument to print only the code lines matching a regular expression, accessible with -g or --grep
+        #[arg(
+            short = 'g',
+            long = "grep",
+            value_name = "PATTERN",
+            conflicts_with_all = ["summary", "fields", "code_only"],
+            help = "Print only the code lines matching a regular expression, under the ID and tags of their snippet"
+        )]
+        grep: Option<String>,
+
+        // Argument to show this many lines around each line matched by --grep, accessible with -C or --context
+        #[arg(
+            short = 'C',
+            long = "context",
+            value_name = "N",
+            default_value_t = 0,
+            requires = "grep",
+            help = "Show N lines before and after each line matched by --grep"
+        )]
+        context: usize,
+
+        // Flag to only show pinned snippets, accessible with -p or --pinned
+        #[arg(short = 'p', long = "pinned", help = "Only show pinned snippets")]
+        pinned: bool,
+
+        // Argument to show at most this many snippets, accessible with --limit
+        #[arg(long = "limit", help = "Show at most this many snippets")]
+        limit: Option<usize>,
+
+        // Argument to skip this many snippets before showing any, accessible with --offset
+        #[arg(
+            long = "offset",
+            default_value_t = 0,
+            help = "Skip this many snippets before showing any"
+        )]
+        offset: usize,
+
+        // Flag to print only the number of matching snippets, accessible with -c or --count
+        #[arg(
+            short = 'c',
+            long = "count",
+            help = "Print only the number of snippets matching the filters"
+        )]
+        count: bool,
+
+        // Flag to print straight to the terminal instead of through the pager, accessible with --no-pager
+        #[arg(long = "no-pager", help = "Do not page long output through $PAGER")]
+        no_pager: bool,
+    },
+}
+
+// Actions of the template subcommand, each template is a `[templates.<name>]` table of the config file
+#[derive(Subcommand)]
+pub enum TemplateAction {
+    // Save a template, replacing one with the same name
+    #[command(about = "Save a template of capture options, replacing one with the same name")]
+    Add {
+        // The name to choose the template with, e.g. "react"
+        #[arg(help = "The name to choose the template with, e.g. react")]
+        name: String,
+
+        // Argument to set the tags filled in, accessible with -t or --tag
+        #[arg(short = 't', long = "tag", help = "Tags to fill in, comma-separated")]
+        tag: Option<String>,
+
+        // Argument to set the description filled in, accessible with -d or --description
+        #[arg(short = 'd', long = "description", help = "Description to fill in")]
+        description: Option<String>,
+
+        // Argument to set the language filled in, accessible with -l or --language
+        #[arg(short = 'l', long = "language", help = "Language to fill in")]
+        language: Option<String>,
+    },
+
+    // List the saved templates
+    #[command(about = "List the saved templates with the values they fill in")]
+    List,
+
+    // Delete a saved template
+    #[command(about = "Delete a saved template")]
+    Remove {
+        // The name of the template to delete
+        #[arg(help = "The name of the template to delete")]
+        name: String,
+    },
+}
+
+fn main() -> ExitCode {
     // Parse the command-line arguments into the CLI struct
     let cli = Cli::parse();
-    
-    // Load the default syntax set for syntax highlighting with newlines
-    let ps = SyntaxSet::load_defaults_newlines();
-    
+
+    // Read the per-user defaults, command-line flags and environment variables win over them
+    let config_path = cli.config.clone().or_else(default_config_path);
+    let mut config = match config_path.as_deref().map(load_config) {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            init_color(cli.no_color);
+            print_error(&err);
+            return ExitCode::FAILURE;
+        }
+        None => Config::default(),
+    };
+    config.color = config.color && !cli.no_color && !no_color_env();
+    config.data_path = Some(
+        cli.data
+            .clone()
+            .map(PathBuf::from)
+            .or(config.data_path)
+            .unwrap_or_else(|| PathBuf::from(DATA_FILE)),
+    );
+    if let Some(dir) = std::env::var_os("CODEVAULT_EXPORT_DIR").filter(|dir| !dir.is_empty()) {
+        config.export_dir = Some(PathBuf::from(dir));
+    }
+
+    // Decide once whether ANSI colors should be emitted for this run
+    init_color(!config.color);
+    init_compact(cli.compact);
+    init_relative_time(cli.relative);
+
+    // Drop decorative output and prompts when running quietly, and encrypt the vault on save if
+    // requested, encrypted vaults are detected when read
+    let ctx = Context::new(config, cli.quiet, cli.yes, cli.encrypt);
+
+    // Run the command and exit non-zero when it failed, so scripts can tell
+    match run(&cli, &ctx, config_path.as_deref()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            // Keep raw copies and exports free of color and off stdout, so errors never end up in a redirected file
+            if matches!(
+                cli.command,
+                Commands::Copy { raw: true, .. } | Commands::Export { stdout: true, .. }
+            ) {
+                eprintln!("error: {}", err);
+            } else {
+                print_error(&err);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Function to execute the selected command, errors are reported by the caller
+fn run(cli: &Cli, ctx: &Context, config_path: Option<&Path>) -> Result<(), CodevaultError> {
+    // Snippets file chosen with --data or CODEVAULT_DATA, then the config file, the default one otherwise
+    let data_file = ctx
+        .config
+        .data_path
+        .as_deref()
+        .unwrap_or(Path::new(DATA_FILE));
+    let data_file = &*data_file.to_string_lossy();
+
+    // The default syntax set, loaded once and shared with the highlighting
+    let ps = syntax_set();
+
     // Collect all supported programming languages into a vector of strings
     let supported_languages: Vec<&str> = ps.syntaxes().iter().map(|s| s.name.as_str()).collect();
 
@@ -238,88 +1082,403 @@ fn main() -> Result<(), String> {
             tag,
             description,
             language,
+            editor,
+            stdin_code,
+            allow_duplicate,
+            append_only,
+            template,
+            theme,
+            source,
+            from_file,
+            append: _,
+            id: append_id,
+            watch,
         } => {
-            // Capture the code snippet from user input
-            let code = capture_snippet();
-            
+            // Options given on the command line win over the ones of the template
+            let template = template
+                .as_deref()
+                .map(|name| find_template(ctx, name))
+                .transpose()?;
+            let from_template = |field: fn(&Template) -> &Option<String>| {
+                template.and_then(|template| field(template).clone())
+            };
+            let tag = &tag.clone().or_else(|| from_template(|t| &t.tag));
+            let description = &description
+                .clone()
+                .or_else(|| from_template(|t| &t.description));
+            let language = &language.clone().or_else(|| from_template(|t| &t.language));
+
+            // Capture from the clipboard until interrupted, the same options apply to every snippet
+            if *watch {
+                if let Some(language) = language {
+                    validate_language(language, &supported_languages)?;
+                }
+                return watch_clipboard(
+                    ctx,
+                    data_file,
+                    tag,
+                    description,
+                    language,
+                    *allow_duplicate,
+                );
+            }
+
+            // Check the theme before any prompt, a typo would otherwise only show when viewing
+            if let Some(theme) = theme {
+                validate_theme(theme)?;
+            }
+
+            // Read the code file up front, so a wrong path fails before any prompt
+            let file_code = match from_file {
+                Some(path) => Some(std::fs::read_to_string(path).map_err(CodevaultError::io(
+                    format!("reading file '{}'", path.display()),
+                ))?),
+                None => None,
+            };
+
+            // Capture the code snippet from the file, the editor or from user input. In a terminal the
+            // code is written in the editor unless typing it in is asked for, so a capture started by
+            // mistake isn't left waiting for 'Ctrl+D'; piped code is read as it is.
+            let read_code = |file_code: Option<String>| match file_code {
+                Some(code) => Ok(code),
+                None if *editor => open_in_editor(""),
+                None if io::stdin().is_terminal() && !*stdin_code => open_in_editor(""),
+                None => capture_snippet(ctx),
+            };
+
+            // Add the code to an existing snippet instead, an ID is only accepted with --append
+            if let Some(append_id) = append_id {
+                // Make sure the snippet exists before asking for any code
+                let filter = ViewFilter {
+                    id: Some(*append_id),
+                    ..ViewFilter::default()
+                };
+                view_snippets(ctx, data_file, &filter)?;
+
+                let code = read_code(file_code)?;
+                if code.trim().is_empty() {
+                    println!(
+                        "{}",
+                        paint("\n\x1b[1;33mNo code entered, the snippet is unchanged.\x1b[0m")
+                    );
+                    return Ok(());
+                }
+                append_to_snippet(ctx, data_file, *append_id, &code)?;
+                ctx.announce(&format!(
+                    "\n\x1b[1;32mThe code has been appended to snippet {}.\x1b[0m",
+                    append_id
+                ));
+                return Ok(());
+            }
+
+            // Check the collection can be read before asking for any code
+            let id = if *append_only {
+                generate_append_id(ctx, data_file)?
+            } else {
+                generate_unique_id(ctx, data_file)?
+            };
+            let file_language = from_file
+                .as_ref()
+                .and_then(|path| detect_language_from_path(path, ps));
+
+            // Ask for the fields left out on the command line, scripts have to pass them
+            let interactive = io::stdin().is_terminal() && !ctx.quiet;
+            let tag = match tag {
+                Some(tag) => tag.clone(),
+                None if interactive => prompt(&style::prompt("Enter tags, comma-separated: ")),
+                None => return Err(CodevaultError::MissingArgument("--tag")),
+            };
+            let description = match description {
+                Some(description) => description.clone(),
+                None if interactive => prompt(&style::prompt("Enter a description: ")),
+                None => return Err(CodevaultError::MissingArgument("--description")),
+            };
+            let language = match language {
+                Some(language) => Some(language.clone()),
+                // The file extension already tells the language
+                None if file_language.is_some() => file_language,
+                None if interactive => Some(prompt(&format!(
+                    "{}{}{}",
+                    style::prompt("Enter the language ("),
+                    style::label("leave blank to detect it from the code"),
+                    style::prompt("): ")
+                )))
+                .filter(|language| !language.is_empty()),
+                None => None,
+            };
+
+            // Reject unknown languages up front, so a typo doesn't silently disable highlighting
+            if let Some(language) = &language {
+                validate_language(language, &supported_languages)?;
+            }
+
+            let code = read_code(file_code)?;
+            if code.trim().is_empty() {
+                println!(
+                    "{}",
+                    paint("\n\x1b[1;33mNo code entered, nothing was captured.\x1b[0m")
+                );
+                return Ok(());
+            }
+
+            // Use the provided language, otherwise try to detect it from the captured code
+            let language = match language {
+                Some(language) => Some(canonical_language(&language)),
+                None => {
+                    let detected = detect_language(&code, ps);
+                    match &detected {
+                        Some(language) => ctx.announce(&format!(
+                            "\n\x1b[1;36mDetected language: \x1b[1;33m{}\x1b[0m",
+                            language
+                        )),
+                        None => ctx.announce(
+                            "\n\x1b[1;36mCould not detect the language, saving as plain text\x1b[0m",
+                        ),
+                    }
+                    detected
+                }
+            };
+
             // Create a new Snippet instance with the provided details
             let new_snippet = Snippet {
-                tag: tag.clone(),
-                description: Some(description.clone()),
+                tag: parse_tags(&tag),
+                description: Some(description).filter(|description| !description.is_empty()),
                 code,
-                timestamp: Local::now().to_string(),
-                language: Some(language.clone()),
-                id: generate_unique_id(DATA_FILE),
+                timestamp: Local::now(),
+                language,
+                id,
+                modified: None,
+                pinned: false,
+                copy_count: 0,
+                // A file the code was read from is its source, unless another one is given
+                source: source
+                    .clone()
+                    .or_else(|| from_file.as_ref().map(|path| path.display().to_string()))
+                    .filter(|source| !source.trim().is_empty()),
+                history: Vec::new(),
+                theme: theme.clone(),
             };
 
             // Save the snippet and handle any errors that may occur
-            if let Err(err) = save_snippet(new_snippet, DATA_FILE) {
-                println!("\x1b[1;31merror:\x1b[0m saving snippet {}", err);
+            if save_snippet(ctx, new_snippet, data_file, *allow_duplicate, *append_only)? {
+                ctx.announce("\n\x1b[1;32mSnippet captured successfully!\x1b[0m\n");
             } else {
-                println!("\n\x1b[1;32mSnippet captured successfully!\x1b[0m\n");
+                println!("{}", paint("\n\x1b[91mSnippet capture cancelled\x1b[0m\n"));
+            }
+        }
+
+        // If the Completions command is selected, write the script for the chosen shell to stdout
+        Commands::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            generate(*shell, &mut command, name, &mut io::stdout());
+        }
+
+        // If the Config command is selected, print where the config is read from or what is in effect
+        Commands::Config { show } => {
+            let location = match config_path {
+                Some(path) if path.exists() => path.display().to_string(),
+                Some(path) => format!("{} (not found, using the defaults)", path.display()),
+                None => "no config directory on this system, using the defaults".to_string(),
+            };
+            if !*show {
+                println!("{}", location);
+                return Ok(());
             }
+
+            // Keep the header a TOML comment, so the output can be saved as a config file
+            ctx.announce(&format!(
+                "\x1b[1m\x1b[36m# Config file: {}\x1b[0m",
+                location
+            ));
+            let settings = toml::to_string(&ctx.config).map_err(|err| CodevaultError::Config {
+                path: location,
+                message: err.to_string(),
+            })?;
+            print!("{}", settings);
         }
-        
+
         // If the Copy command is selected
-        Commands::Copy { id } => match copy_code(DATA_FILE, id) {
-            Ok(snippet) => {
-                println!("\n\x1b[1;38;5;201mCode:\x1b[0m\n");
+        Commands::Copy { id, raw, language } => {
+            // Check the override before picking, so a typo fails right away
+            if let Some(language) = language {
+                validate_language(language, &supported_languages)?;
+            }
 
-                // Highlight the code snippet if a language is specified, otherwise print it as-is
-                let highlighted_code = if let Some(lang) = &snippet.language {
-                    highlight_code_snippets(&snippet.code, lang)
-                } else {
-                    snippet.code.clone()
-                };
+            // Without an ID, let the user pick the snippet when typing into a terminal
+            let id = match id {
+                None if io::stdin().is_terminal() && !ctx.quiet => {
+                    match pick_snippet(ctx, data_file)? {
+                        Some(id) => Some(id),
+                        None => {
+                            eprintln!("{}", paint("\n\x1b[91mSnippet copy cancelled\x1b[0m"));
+                            return Ok(());
+                        }
+                    }
+                }
+                id => *id,
+            };
+            // Scripts reading the raw code aren't copies by the user, so they don't count
+            let snippet = copy_code(ctx, data_file, &id, !*raw)?;
+
+            if *raw {
+                // Write the stored code byte for byte, without adding a trailing newline
+                let mut stdout = io::stdout();
+                stdout
+                    .write_all(snippet.code.as_bytes())
+                    .and_then(|_| stdout.flush())
+                    .map_err(CodevaultError::io("writing code to stdout"))?;
+            } else {
+                ctx.announce("\n\x1b[1;38;5;201mCode:\x1b[0m\n");
+
+                // Highlight the code snippet if a language is specified, otherwise print it as-is,
+                // a language given on the command line wins over the stored one
+                let highlighted_code =
+                    if let Some(lang) = language.as_ref().or(snippet.language.as_ref()) {
+                        highlight_code_snippets(
+                            &snippet.code,
+                            lang,
+                            snippet.theme.as_deref(),
+                            &ctx.config.theme,
+                        )
+                    } else {
+                        snippet.code.clone()
+                    };
 
                 println!("{}", highlighted_code);
             }
-            Err(err) => {
-                println!("\x1b[1m\x1b[31merror:\x1b[0m\x1b[1m  {}\x1b[0m", err);
-            }
-        },
+        }
 
         // If the Delete command is selected
-        Commands::Delete { id } => {
-            // If an ID string is provided, parse it into a vector of IDs and delete the corresponding snippets
-            if let Some(id_str) = id {
-                let ids: Vec<u32> = id_str
-                    .split(',')
-                    .map(|id| id.trim().parse::<u32>())
-                    .collect::<Result<Vec<u32>, _>>()
-                    .map_err(|err| format!("Invalid ID format: {}", err))?;
-
-                match delete_snippet(DATA_FILE, &ids) {
-                    Ok(_) => {}
-                    Err(err) => println!("\x1b[31merror:\x1b[0m {}", err),
-                }
+        Commands::Delete {
+            id,
+            tag,
+            language,
+            all,
+            exact,
+            dry_run,
+        } => {
+            // Emptying the collection has its own, stronger confirmation
+            if *all {
+                return delete_all_snippets(ctx, data_file, *dry_run);
+            }
+
+            // Parse the comma-separated IDs if given, otherwise select every snippet matching the
+            // tag and/or language, clap makes sure at least one selector is present
+            let ids = match id {
+                Some(id_str) => parse_ids(id_str)?,
+                None => matching_snippet_ids(ctx, data_file, tag, language, *exact)?,
+            };
+
+            delete_snippet(ctx, data_file, &ids, *dry_run)?;
+        }
+
+        // If the Diff command is selected
+        Commands::Diff { ids } => {
+            let [old_id, new_id] = ids[..] else {
+                return Err(CodevaultError::DiffNeedsTwoIds(ids.len()));
+            };
+            ctx.announce(&format!(
+                "\n\x1b[38;5;201;1mDiff of snippets {} and {}:\x1b[0m\n",
+                old_id, new_id
+            ));
+            diff_snippets(ctx, data_file, old_id, new_id)?;
+        }
+
+        // If the Duplicate command is selected
+        Commands::Duplicate { id, edit } => {
+            let new_id = duplicate_snippet(ctx, data_file, *id)?;
+            if ctx.quiet {
+                // The new ID is the data a script needs
+                println!("{}", new_id);
             } else {
-                // Handle the case where no valid ID is provided
-                match id.as_ref().and_then(|s| s.trim().parse::<u32>().ok()) {
-                    Some(id) => match delete_snippet(DATA_FILE, &[id]) {
-                        Ok(_) => {}
-                        Err(err) => println!("\x1b[31merror:\x1b[0m {}", err),
-                    },
-                    None => {
-                        println!("\x1b[31merror:\x1b[0m missing snippet ID.
-                        \nPlease provide a snippet ID using the \x1b[1m\x1b[36m-i\x1b[0m or \x1b[1m\x1b[36m--id\x1b[0m flag.
-                        \n\x1b[1m\x1b[32m\x1b[4mUsage:\x1b[0m \x1b[1m\x1b[36mcodevault delete\x1b[0m \x1b[1m\x1b[36m-i\x1b[0m \x1b[34m<ID>\x1b[0m
-                        \n\x1b[1m\x1b[32m\x1b[4mExample:\x1b[0m \x1b[1m\x1b[36mcodevault delete\x1b[0m \x1b[1m\x1b[36m-i\x1b[0m \x1b[1m\x1b[34m7\x1b[0m
-                        \nFor more information, try '\x1b[1m\x1b[36m--help\x1b[0m'");
+                println!(
+                    "{}",
+                    paint(&format!(
+                        "\n\x1b[1;32mSnippet {} has been duplicated as ID {}.\x1b[0m",
+                        id, new_id
+                    ))
+                );
+            }
+
+            // Continue with the interactive edit of the copy if requested
+            if *edit {
+                let outcome = edit_snippet(
+                    ctx,
+                    data_file,
+                    &Some(new_id),
+                    &None,
+                    &EditFields::default(),
+                    false,
+                    false,
+                )?;
+                match outcome {
+                    EditOutcome::Saved => {
+                        ctx.announce("\n\x1b[1;32mChanges have been applied to the snippet.\x1b[0m")
                     }
-                };
+                    EditOutcome::Unchanged => {
+                        println!(
+                            "{}",
+                            paint("\n\x1b[1;33mNo changes made, the copy is identical.\x1b[0m")
+                        )
+                    }
+                    EditOutcome::Cancelled => {
+                        println!(
+                            "{}",
+                            paint("\n\x1b[91mEdit cancelled, the copy was kept unchanged.\x1b[0m")
+                        )
+                    }
+                }
             }
         }
 
         // If the Edit command is selected
-        Commands::Edit { id, tag, language } => {
-            // Edit the snippet with the provided ID, tag, or language and update the data file
-            match edit_snippet(DATA_FILE, id, tag, language, &supported_languages) {
-                Ok(_) => {
-                    println!("\n\x1b[1;32mChanges have been applied to the snippet.\x1b[0m");
+        Commands::Edit {
+            id,
+            tag,
+            language,
+            description,
+            theme,
+            editor,
+            dry_run,
+        } => {
+            // Check the theme up front, an empty one is allowed to clear it
+            if let Some(theme) = theme.as_deref().filter(|theme| !theme.is_empty()) {
+                validate_theme(theme)?;
+            }
+
+            // Fields given on the command line are updated directly, skipping the interactive prompts
+            let fields = EditFields {
+                language: language.clone(),
+                description: description.clone(),
+                theme: theme.clone(),
+            };
+
+            // Edit the snippet with the provided ID or tag and update the data file
+            let outcome = edit_snippet(ctx, data_file, id, tag, &fields, *editor, *dry_run)?;
+            match outcome {
+                EditOutcome::Cancelled => {
+                    println!(
+                        "{}",
+                        paint("\n\x1b[91mEdit cancelled, nothing was saved.\x1b[0m")
+                    )
                 }
-                Err(err) => {
-                    println!("\n\x1b[31merror:\x1b[0m{}\n", err);
+                EditOutcome::Unchanged => {
+                    println!(
+                        "{}",
+                        paint(
+                            "\n\x1b[1;33mNo changes made, the snippet was left as it was.\x1b[0m"
+                        )
+                    )
+                }
+                EditOutcome::Saved if *dry_run => {
+                    println!(
+                        "{}",
+                        paint("\n\x1b[1;33mDry run, no changes were saved.\x1b[0m")
+                    )
+                }
+                EditOutcome::Saved => {
+                    ctx.announce("\n\x1b[1;32mChanges have been applied to the snippet.\x1b[0m")
                 }
             }
         }
@@ -330,973 +1489,625 @@ fn main() -> Result<(), String> {
             language,
             tag,
             path,
-        } => match export_snippets(DATA_FILE, id, tag, language, path) {
-            Ok(_) => {}
-            Err(err) => println!("\x1b[31merror:\x1b[0m {}", err),
-        },
+            format,
+            force,
+            output,
+            plain,
+            stdout,
+            line_endings,
+            verbose,
+            exact,
+            since_last,
+        } => {
+            let options = ExportOptions {
+                path: path.clone(),
+                output: output.clone(),
+                format: *format,
+                force: *force,
+                plain: *plain,
+                stdout: *stdout,
+                line_endings: *line_endings,
+                verbose: *verbose,
+                exact: *exact,
+                since_last: *since_last,
+            };
+            // An empty list exports every snippet matching the other filters
+            let ids = match id {
+                Some(id_str) => parse_ids(id_str)?,
+                None => Vec::new(),
+            };
+            export_snippets(ctx, data_file, &ids, tag, language, &options)?;
+        }
 
-        // If the Languages command is selected
-        Commands::Languages => {
-            // Display all supported programming languages
-            println!("\n\x1b[38;5;201;1mSupported Languages:\x1b[0m\n");
-            for language in &supported_languages {
-                println!("\x1b[1;36m»\x1b[0m \x1b[1;33m{}\x1b[0m", language);
+        // If the History command is selected
+        Commands::History { id } => {
+            let snippet = snippet_history(ctx, data_file, *id)?;
+            if snippet.history.is_empty() {
+                println!(
+                    "{}",
+                    paint(&format!(
+                        "\n\x1b[1;33mSnippet {} has no previous versions.\x1b[0m",
+                        id
+                    ))
+                );
+                return Ok(());
             }
-        }
 
-        // If the View command is selected
-        Commands::View {
-            id,
-            tag,
-            language,
-            keyword,
-            summary,
-        } => {
-            println!("\n\x1b[38;5;201;1mSnippets Collection:\x1b[0m\n");
+            ctx.announce(&format!(
+                "\n\x1b[38;5;201;1mHistory of snippet {}:\x1b[0m\n",
+                id
+            ));
 
-            // View and print snippets based on the provided filters (ID, tag, language, keyword, or summary)
-            match view_snippets(DATA_FILE, id, tag, language, keyword, *summary) {
-                Ok(snippets) => {
-                    for snippet in snippets {
-                        if *summary {
-                            print_snippet_summary(&snippet);
-                        } else {
-                            print_snippet(&snippet);
-                        }
-                    }
-                }
-                Err(err) => println!("\x1b[31merror:\x1b[0m{}", err),
+            // Show each version's code in a box of its own, highlighted like the current code
+            let display = DisplayOptions {
+                fields: vec![SnippetField::Code],
+                theme: ctx.config.theme.clone(),
+                ..DisplayOptions::default()
+            };
+            for (index, version) in snippet.history.iter().enumerate() {
+                println!(
+                    "{}",
+                    paint(&format!(
+                        "\x1b[1;33mVersion {}\x1b[0m \x1b[2m(saved {})\x1b[0m",
+                        index + 1,
+                        format_timestamp(&version.timestamp)
+                    ))
+                );
+                let old = Snippet {
+                    code: version.code.clone(),
+                    ..snippet.clone()
+                };
+                print!("{}", render_snippet(&old, &display));
             }
         }
-    }
 
-    // Return an OK result to indicate successful execution
-    Ok(())
-}
+        // If the Import command is selected, read the whole JSON first and add its snippets in one go
+        Commands::Import { file, lenient } => {
+            let (content, origin) = match file {
+                Some(path) if path.as_os_str() != "-" => (
+                    std::fs::read_to_string(path).map_err(CodevaultError::io(format!(
+                        "reading file '{}'",
+                        path.display()
+                    )))?,
+                    path.display().to_string(),
+                ),
+                _ => {
+                    let mut content = String::new();
+                    io::stdin()
+                        .read_to_string(&mut content)
+                        .map_err(CodevaultError::io("reading stdin"))?;
+                    (content, "stdin".to_string())
+                }
+            };
 
-fn generate_unique_id(file_path: &str) -> u32 {
-    let mut max_id = 0;
-    
-    // Open the file specified by file_path
-    if let Ok(mut file) = File::open(file_path) {
-        // Attempt to deserialize the file content into a Vec<Snippet>
-        let snippets: Vec<Snippet> = match serde_json::from_reader(&mut file) {
-            Ok(s) => s,
-            Err(_err) => {
-                // If deserialization fails, start with ID 1
-                return 1;
+            let summary = import_snippets(ctx, data_file, &content, &origin, *lenient)?;
+            for (index, reason) in &summary.skipped {
+                eprintln!(
+                    "{}",
+                    paint(&format!(
+                        "\x1b[1;33mwarning:\x1b[0m skipped entry {}: {}",
+                        index + 1,
+                        reason
+                    ))
+                );
             }
-        };
-        
-        // Find the maximum ID from the existing snippets, default to 0 if no snippets are found
-        max_id = snippets.iter().map(|s| s.id).max().unwrap_or(0);
-    }
-    
-    // Return the next unique ID by incrementing the maximum ID found
-    max_id + 1
-}
-
-fn format_with_border(content: &str, width: usize) -> String {
-    // Remove ANSI color codes from content to calculate the width correctly
-    let stripped_content = strip_ansi_codes(content);
-    
-    // Calculate the amount of padding needed to make the total width equal to 'width'
-    let padding = width.saturating_sub(stripped_content.chars().count());
-    
-    // Format content with borders and padding to fit the specified width
-    format!(
-        "\x1b[34m║\x1b[0m{}{}\x1b[34m║\x1b[0m",
-        content,
-        " ".repeat(padding)
-    )
-}
-
-fn print_formatted_code(code: &str, language: &Option<String>, width: usize) {
-    // Highlight the code if a language is specified, otherwise use the plain code
-    let highlighted_code = if let Some(lang) = language {
-        highlight_code_snippets(code, lang)
-    } else {
-        code.to_string()
-    };
-
-    // Print the header for the code section with a border
-    println!(
-        "{}",
-        format_with_border(&format!("\x1b[33;1m  Code:\x1b[0m"), width)
-    );
-    
-    // Print each line of the highlighted code with a border
-    for line in highlighted_code.lines() {
-        let formatted_line = format!("  {}", line);
-        println!("{}", format_with_border(&formatted_line, width));
-    }
-}
-
-fn highlight_code_snippets(code: &str, language: &str) -> String {
-    // Load default syntax settings and themes
-    let ps = SyntaxSet::load_defaults_newlines();
-    let ts = ThemeSet::load_defaults();
-
-    // Find the syntax definition based on the provided language name or token
-    let syntax = ps
-        .find_syntax_by_token(language)
-        .or_else(|| ps.find_syntax_by_name(language))
-        .unwrap_or(ps.find_syntax_plain_text());
-
-    let mut output = String::new();
-    
-    // Iterate through each line of the code with its endings
-    for line in LinesWithEndings::from(code) {
-        // Create a highlighter with the chosen syntax and theme
-        let mut highlighter = HighlightLines::new(syntax, &ts.themes["base16-ocean.dark"]);
-        
-        // Highlight the current line, collecting style and text tuples
-        let ranges: Vec<(Style, &str)> = highlighter.highlight_line(line, &ps).unwrap();
-        
-        // Format the highlighted line into terminal color codes
-        let escaped_line = format_terminal_snippets(&ranges);
-        
-        // Append the formatted line to the output
-        output.push_str(&escaped_line);
-    }
-
-    output
-}
-
-fn format_terminal_snippets(v: &[(Style, &str)]) -> String {
-    let mut s = String::new();
-    
-    // Iterate through each style-text pair
-    for &(ref style, text) in v.iter() {
-        // Format text with ANSI color codes based on the style
-        s.push_str(&format!(
-            "\x1b[38;2;{};{};{}m{}",
-            style.foreground.r, style.foreground.g, style.foreground.b, text
-        ));
-    }
-    
-    // Reset ANSI color codes to default
-    s.push_str("\x1b[0m");
-    
-    s
-}
-
-fn print_snippet(snippet: &Snippet) {
-    // Format the snippet ID line with ANSI color codes
-    let id_line = format!("  \x1b[33;1mID:\x1b[0m \x1b[35;1m{}\x1b[0m", snippet.id);
-    
-    // Format the creation timestamp line with ANSI color codes
-    let created_line = format!(
-        "  \x1b[33;1mCreated:\x1b[0m \x1b[35;1m{}\x1b[0m",
-        snippet.timestamp
-    );
-    
-    // Format the tag line with ANSI color codes
-    let tag_line = format!(
-        "  \x1b[33;1mSnippet's Tag:\x1b[0m \x1b[35;1m{}\x1b[0m",
-        snippet.tag
-    );
-    
-    // Format the description line if a description is available
-    let description_line = if let Some(desc) = &snippet.description {
-        format!("  \x1b[33;1mDescription:\x1b[0m \x1b[35;1m{}\x1b[0m", desc)
-    } else {
-        String::new()
-    };
-
-    // Collect all lines into a vector, stripping ANSI color codes for length calculation
-    let all_lines = vec![
-        strip_ansi_codes(&id_line),
-        strip_ansi_codes(&tag_line),
-        strip_ansi_codes(&created_line),
-        strip_ansi_codes(&description_line),
-    ]
-    .into_iter()
-    .chain(snippet.code.lines().map(|line| strip_ansi_codes(line)))
-    .collect::<Vec<_>>();
-
-    // Determine the maximum line length for formatting
-    let max_line_length = all_lines
-        .iter()
-        .map(|line| line.chars().count())
-        .max()
-        .unwrap_or(0);
-
-    // Set the width for the formatted output, adding extra space for borders
-    let adjusted_width = max_line_length + 4;
-
-    // Print the top border of the snippet box
-    println!(
-        "\x1b[34m{}\x1b[0m",
-        "\x1b[34m╔\x1b[0m".to_owned()
-            + &"\x1b[34m═\x1b[0m".repeat(adjusted_width)
-            + "\x1b[34m╗\x1b[0m"
-    );
-    
-    // Print each formatted line within the border
-    println!("{}", format_with_border(&id_line, adjusted_width));
-    println!("{}", format_with_border(&tag_line, adjusted_width));
-    println!("{}", format_with_border(&created_line, adjusted_width));
-    if !description_line.is_empty() {
-        println!("{}", format_with_border(&description_line, adjusted_width));
-    }
-    
-    // Print a separator line within the snippet box
-    println!(
-        "\x1b[34m{}\x1b[0m",
-        "\x1b[34m╟\x1b[0m".to_owned()
-            + &"\x1b[34m─\x1b[0m".repeat(adjusted_width)
-            + "\x1b[34m╢\x1b[0m"
-    );
-    
-    // Print the code inside the snippet box with formatting
-    print_formatted_code(&snippet.code, &snippet.language, adjusted_width);
-    
-    // Print the bottom border of the snippet box
-    println!(
-        "\x1b[34m{}\x1b[0m",
-        "\x1b[34m╚\x1b[0m".to_owned()
-            + &"\x1b[34m═\x1b[0m".repeat(adjusted_width)
-            + "\x1b[34m╝\x1b[0m\n"
-    );
-}
-
-fn print_snippet_summary(snippet: &Snippet) {
-    // Format the snippet ID line with ANSI color codes
-    let id_line = format!("  \x1b[33;1mID:\x1b[0m \x1b[35;1m{}\x1b[0m", snippet.id);
-    
-    // Format the tag line with ANSI color codes
-    let tag_line = format!("  \x1b[33;1mTag:\x1b[0m \x1b[35;1m{}\x1b[0m", snippet.tag);
-    
-    // Format the creation timestamp line with ANSI color codes
-    let created_line = format!(
-        "  \x1b[33;1mCreated:\x1b[0m \x1b[35;1m{}\x1b[0m",
-        snippet.timestamp
-    );
-    
-    // Format the description line if a description is available
-    let description_line = if let Some(desc) = &snippet.description {
-        format!("  \x1b[33;1mDescription:\x1b[0m \x1b[35;1m{}\x1b[0m", desc)
-    } else {
-        String::new()
-    };
-
-    // Collect all lines into a vector, stripping ANSI color codes for length calculation
-    let all_lines = vec![
-        strip_ansi_codes(&id_line),
-        strip_ansi_codes(&tag_line),
-        strip_ansi_codes(&created_line),
-        strip_ansi_codes(&description_line),
-    ]
-    .into_iter()
-    .collect::<Vec<_>>();
-
-    // Determine the maximum line length for formatting
-    let max_line_length = all_lines
-        .iter()
-        .map(|line| line.chars().count())
-        .max()
-        .unwrap_or(0);
-
-    // Set the width for the formatted output, adding extra space for borders
-    let adjusted_width = max_line_length + 4;
-
-    // Print the top border of the summary box
-    println!(
-        "\x1b[34m{}\x1b[0m",
-        "\x1b[34m╔\x1b[0m".to_owned()
-            + &"\x1b[34m═\x1b[0m".repeat(adjusted_width)
-            + "\x1b[34m╗\x1b[0m"
-    );
-    
-    // Print each formatted line within the border
-    println!("{}", format_with_border(&id_line, adjusted_width));
-    println!("{}", format_with_border(&tag_line, adjusted_width));
-    println!("{}", format_with_border(&created_line, adjusted_width));
-    if !description_line.is_empty() {
-        println!("{}", format_with_border(&description_line, adjusted_width));
-    }
-    
-    // Print the bottom border of the summary box
-    println!(
-        "\x1b[34m{}\x1b[0m",
-        "\x1b[34m╚\x1b[0m".to_owned()
-            + &"\x1b[34m═\x1b[0m".repeat(adjusted_width)
-            + "\x1b[34m╝\x1b[0m\n"
-    );
-}
-
-// Function to capture a code snippet from standard input
-fn capture_snippet() -> String {
-    let mut buffer = String::new(); // Create a buffer to store the input
-    println!("\n\x1b[38;5;201;1mCapture snippet:\x1b[0m\n");
-    println!("\x1b[1;36m Enter your code snippet (press \x1b[33m'Return'\x1b[1;36m, then \x1b[33m'Ctrl+D'\x1b[1;36m to finish):\x1b[0m");
-    println!("\x1b[1;31m Note:\x1b[0m \x1b[1;33m'Arrow Keys'\x1b[0m \x1b[1;36mare not captured, use \x1b[1;33m'Backspace'\x1b[0m \x1b[1;36mto erase inputs\x1b[0m");
-    
-    // Read the entire input into the buffer
-    io::stdin()
-        .read_to_string(&mut buffer)
-        .expect("\x1b[1;31mfailed to read snippet from input\x1b[0m");
-    buffer
-}
-
-// Function to save a snippet to a JSON file
-fn save_snippet(snippet: Snippet, file_path: &str) -> Result<(), String> {
-    // Attempt to open the file and deserialize existing snippets
-    let mut snippets: Vec<Snippet> = if let Ok(file) = File::open(file_path) {
-        serde_json::from_reader(file).map_err(|err| {
-            format!(
-                "\x1b[31merror:\x1b[0m unable deserializing snippets {}",
-                err
-            )
-        })?
-    } else {
-        Vec::new() // If file does not exist, start with an empty vector
-    };
+            let imported = summary.imported.len();
+            let mut message = format!(
+                "\n\x1b[1;32mImported {} {}",
+                imported,
+                if imported == 1 { "snippet" } else { "snippets" }
+            );
+            match summary.imported.as_slice() {
+                [] => {}
+                [id] => message.push_str(&format!(" as ID {}", id)),
+                [first, .., last] => message.push_str(&format!(" as ID {}–{}", first, last)),
+            }
+            if !summary.skipped.is_empty() {
+                message.push_str(&format!(", skipped {} invalid", summary.skipped.len()));
+            }
+            ctx.announce(&(message + ".\x1b[0m"));
+        }
 
-    snippets.push(snippet); // Add the new snippet to the vector
+        // If the Languages command is selected
+        Commands::Languages { used: true, .. } => {
+            ctx.announce("\n\x1b[38;5;201;1mLanguages in your Collection:\x1b[0m\n");
+            print_used_languages(ctx, data_file)?;
+        }
+        Commands::Languages {
+            used: false,
+            filter,
+            count,
+        } => {
+            // Keep the languages whose name contains the filter, ignoring case
+            let filter = filter.as_deref().unwrap_or("").to_lowercase();
+            let languages: Vec<&&str> = supported_languages
+                .iter()
+                .filter(|language| language.to_lowercase().contains(&filter))
+                .collect();
 
-    // Open the file for writing and truncate it to overwrite existing content
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(file_path)
-        .map_err(|err| format!("\x1b[1;33m{}\x1b[0m", err))?;
+            // Only print how many languages match
+            if *count {
+                println!("{}", languages.len());
+                return Ok(());
+            }
 
-    // Serialize the snippets vector to the file
-    serde_json::to_writer_pretty(&mut file, &snippets)
-        .map_err(|err| format!(" serializing snippets: '\x1b[1;33m{}\x1b[0m'", err))?;
+            // Display the matching supported programming languages
+            ctx.announce("\n\x1b[38;5;201;1mSupported Languages:\x1b[0m\n");
+            if languages.is_empty() {
+                println!(
+                    "{}",
+                    paint(&format!(
+                        "\x1b[1;33mNo supported language matches '{}'.\x1b[0m",
+                        filter
+                    ))
+                );
+            }
+            for language in languages {
+                println!(
+                    "{}",
+                    paint(&format!("\x1b[1;36m»\x1b[0m \x1b[1;33m{}\x1b[0m", language))
+                );
+            }
+        }
 
-    Ok(())
-}
+        // If the Loc command is selected
+        Commands::Loc => {
+            ctx.announce("\n\x1b[38;5;201;1mLines of Code:\x1b[0m\n");
+            print_loc(ctx, data_file)?;
+        }
 
-// Function to load snippets from a JSON file
-fn load_snippets(file_path: &str) -> Result<Vec<Snippet>, String> {
-    // Open the file and read its content into a vector of snippets
-    let file =
-        File::open(file_path).map_err(|err| format!("\x1b[1;33m opening file {}\x1b[0m", err))?;
-    let snippets: Vec<Snippet> = serde_json::from_reader(file)
-        .map_err(|err| format!("\x1b[1;33m reading snippets{}\x1b[0m", err))?;
-    Ok(snippets)
-}
+        // If the Merge command is selected
+        Commands::Merge {
+            into,
+            from,
+            remove_source,
+        } => {
+            merge_snippets(ctx, data_file, *into, *from, *remove_source)?;
+            ctx.announce(&format!(
+                "\n\x1b[1;32mThe code of snippet {} has been appended to snippet {}.\x1b[0m",
+                from, into
+            ));
+            if *remove_source {
+                ctx.announce(&format!(
+                    "\x1b[1;32mSnippet {} has been deleted.\x1b[0m \x1b[2m(run `codevault undo` to restore)\x1b[0m",
+                    from
+                ));
+            }
+        }
 
-// Function to view snippets based on various filters like ID, tag, language, and keyword
-fn view_snippets(
-    file_path: &str,
-    id: &Option<u32>,
-    tag: &Option<String>,
-    language: &Option<String>,
-    keyword: &Option<String>,
-    _summary: bool,
-) -> Result<Vec<Snippet>, String> {
-    // Load all snippets from the specified file
-    let snippets = load_snippets(file_path)?;
-
-    // Filter snippets based on provided criteria
-    let mut filtered_snippets = snippets
-        .into_iter()
-        .filter(|snippet| {
-            // Check if the snippet's tag matches any of the provided tags
-            let tag_match = if let Some(tag) = tag {
-                let tags: Vec<&str> = tag.split(',').map(|s| s.trim()).collect();
-                tags.iter()
-                    .any(|t| snippet.tag.to_lowercase().contains(&t.to_lowercase()))
+        // If the NormalizeTags command is selected
+        Commands::NormalizeTags { case } => {
+            let updated = normalize_snippet_tags(ctx, data_file, *case)?;
+            if updated == 0 {
+                ctx.announce(
+                    "\n\x1b[1;32mAll tags are already in this case, nothing to change.\x1b[0m",
+                );
             } else {
-                true
-            };
+                ctx.announce(&format!(
+                    "\n\x1b[1;32mNormalized the tags of {} {}.\x1b[0m",
+                    updated,
+                    if updated == 1 { "snippet" } else { "snippets" }
+                ));
+            }
+        }
 
-            // Check if the snippet's language matches any of the provided languages
-            let language_match = if let Some(language) = language {
-                let langs: Vec<&str> = language.split(',').map(|s| s.trim()).collect();
-                langs.iter().any(|l| {
-                    snippet
-                        .language
-                        .as_ref()
-                        .map(|lang| lang.to_lowercase().contains(&l.to_lowercase()))
-                        .unwrap_or(false)
-                })
-            } else {
-                true
+        // If the Open command is selected, show the snippet in the editor and throw the copy away afterwards
+        Commands::Open { id } => {
+            // Without an ID, let the user pick the snippet when typing into a terminal
+            let id = match id {
+                Some(id) => *id,
+                None if io::stdin().is_terminal() && !ctx.quiet => {
+                    match pick_snippet(ctx, data_file)? {
+                        Some(id) => id,
+                        None => return Ok(()),
+                    }
+                }
+                None => return Err(CodevaultError::MissingArgument("--id")),
             };
-
-            // Check if the snippet contains any of the provided keywords in its tag, description, or code
-            let keyword_match = if let Some(keyword) = keyword {
-                let keywords: Vec<&str> = keyword.split(',').map(|s| s.trim()).collect();
-                keywords.iter().any(|k| {
-                    snippet.tag.to_lowercase().contains(&k.to_lowercase())
-                        || snippet
-                            .description
-                            .as_ref()
-                            .map(|desc| desc.to_lowercase().contains(&k.to_lowercase()))
-                            .unwrap_or(false)
-                        || snippet.code.to_lowercase().contains(&k.to_lowercase())
-                })
-            } else {
-                true
+            let filter = ViewFilter {
+                id: Some(id),
+                ..ViewFilter::default()
             };
+            let snippet = view_snippets(ctx, data_file, &filter)?.remove(0);
+            view_in_editor(&snippet)?;
+        }
 
-            tag_match && language_match && keyword_match
-        })
-        .collect::<Vec<_>>(); // Collect the filtered snippets into a vector
-
-    // If an ID is specified, filter to include only the snippet with that ID
-    if let Some(id) = id {
-        if let Some(index) = filtered_snippets.iter().position(|s| s.id == *id) {
-            filtered_snippets = vec![filtered_snippets[index].clone()];
-        } else {
-            return Err(format!(
-                " snippet ID '\x1b[1;33m{}\x1b[0m' does not exist in the collection",
+        // If the Pin command is selected
+        Commands::Pin { id } => {
+            set_pinned(ctx, data_file, *id, true)?;
+            ctx.announce(&format!(
+                "\n\x1b[1;32mSnippet {} has been pinned.\x1b[0m",
                 id
             ));
         }
-    }
 
-    Ok(filtered_snippets) // Return the filtered snippets
-}
-
-fn edit_snippet(
-    file_path: &str,
-    id: &Option<u32>,
-    tag: &Option<String>,
-    _language: &Option<String>,
-    _supported_languages: &Vec<&str>,
-) -> Result<(), String> {
-    // Load existing snippets from the file
-    let mut snippets = load_snippets(file_path)?;
-
-    // Determine which snippet to edit based on ID or tag
-    let mut snippet_to_edit = match (id, tag) {
-        // If an ID is provided, find the snippet with that ID
-        (Some(snippet_id), _) => {
-            if let Some(index) = snippets.iter().position(|s| s.id == *snippet_id) {
-                snippets.remove(index)
-            } else {
-                return Err(format!(
-                    "Snippet ID '\x1b[1;33m{}\x1b[0m' does not exist in the collection",
-                    snippet_id
-                ));
+        // If the Recent command is selected
+        Commands::Recent { count } => {
+            let snippets = recent_snippets(ctx, data_file, *count)?;
+            ctx.announce("\n\x1b[38;5;201;1mRecent Snippets:\x1b[0m\n");
+            for snippet in &snippets {
+                print_snippet_summary(snippet, &ctx.config.theme);
             }
         }
-        // If no ID is provided but a tag is, find snippets with that tag
-        (None, Some(snippet_tag)) => {
-            let matching_snippets: Vec<&Snippet> = snippets
-                .iter()
-                .filter(|s| s.tag.to_lowercase().contains(&snippet_tag.to_lowercase()))
-                .collect();
 
-            // Handle cases where no snippets or multiple snippets match the tag
-            if matching_snippets.is_empty() {
-                return Err(format!(
-                    "Tag '\x1b[1;33m{}\x1b[0m' doesn't match any snippets. Try a different tag.",
-                    snippet_tag
-                ));
-            } else if matching_snippets.len() > 1 {
-                println!("\n\x1b[38;5;201;1mEdit snippet:\x1b[0m\n");
-                println!("\x1b[1;36mMultiple matching tags found, choose an \x1b[1;33mID\x1b[1;36m to edit from list:\x1b[0m\n");
-                for snippet in matching_snippets.iter() {
-                    println!("\x1b[1;36m  »\x1b[0m \x1b[1;33mID {}\x1b[0m", snippet.id);
-                }
-
-                // Prompt the user to select an ID to edit
-                loop {
-                    print!("\n\x1b[1;36mType the \x1b[1;33mID\x1b[0m\x1b[1;36m of the snippet you want to modify: \x1b[0m");
-                    io::stdout().flush().unwrap(); 
-                    let mut input = String::new();
-                    io::stdin().read_line(&mut input).unwrap();
-                    let input_trimmed = input.trim();
-
-                    if let Ok(chosen_id) = input_trimmed.parse::<u32>() {
-                        if matching_snippets.iter().any(|s| s.id == chosen_id) {
-                            if let Some(index) = snippets.iter().position(|s| s.id == chosen_id) {
-                                break snippets.remove(index);
-                            }
-                        } else {
-                            println!(
-                                "\x1b[1;31mID '\x1b[1;33m{}\x1b[0m\x1b[1;31m' is not in the list. Please choose a valid ID.\x1b[0m",
-                                chosen_id
-                            );
-                        }
-                    } else {
-                        println!(
-                            "\x1b[1;31mInvalid input. Please enter a valid numeric ID from the list.\x1b[0m"
-                        );
-                    }
-                }
-            } else {
-                matching_snippets[0].clone()
+        // If the Repair command is selected
+        Commands::Repair => {
+            let renumbered = repair_snippets(ctx, data_file)?;
+            if renumbered.is_empty() {
+                ctx.announce("\n\x1b[1;32mNo duplicate IDs found, nothing to repair.\x1b[0m");
+            }
+            for (old, new) in renumbered {
+                println!(
+                    "{}",
+                    paint(&format!(
+                        "\x1b[1;32mA duplicate of ID {} is now ID {}.\x1b[0m",
+                        old, new
+                    ))
+                );
             }
         }
-        // If neither ID nor tag is provided, return an error
-        (None, None) => {
-            println!("\x1b[1;36mTo edit a snippet, use its \x1b[1;33mID\x1b[1;36m or \x1b[1;33mtag\x1b[0m\x1b[1;36m\x1b[0m");
-            return Err("Enter a snippet ID or tag to proceed.".to_string());
-        }
-    };
-
-    // Display current snippet details to the user
-    println!("\n\x1b[38;5;201;1mEdit snippet:\x1b[0m\n");
-
-    let id_line = format!("  \x1b[33;1mID:\x1b[0m \x1b[35;1m{}\x1b[0m", snippet_to_edit.id);
-    let created_line = format!(
-        "  \x1b[33;1mCreated:\x1b[0m \x1b[35;1m{}\x1b[0m",
-        snippet_to_edit.timestamp
-    );
-    let tag_line = format!(
-        "  \x1b[33;1mCurrent Snippet's Tag:\x1b[0m \x1b[35;1m{}\x1b[0m",
-        snippet_to_edit.tag
-    );
-    let description_line = if let Some(desc) = &snippet_to_edit.description {
-        format!(
-            "  \x1b[33;1mCurrent Description:\x1b[0m \x1b[35;1m{}\x1b[0m",
-            desc
-        )
-    } else {
-        String::new()
-    };
-
-    // Find the length of the longest line for formatting purposes
-    let longest_line = [
-        id_line.len(),
-        created_line.len(),
-        tag_line.len(),
-        description_line.len(),
-    ]
-    .iter()
-    .max()
-    .unwrap()
-    .clone(); 
-
-    // Create a border string based on the longest line length
-    let border_string = "═".repeat(longest_line);
-
-    // Print the snippet details with a formatted border
-    println!(
-        "\x1b[34;1m╔{}\x1b[0m",
-        border_string.clone() + "╗\x1b[34;1m\x1b[0m"
-    );
-    println!("{}", id_line);
-    println!("{}", tag_line);
-    println!("{}", created_line);
-    if !description_line.is_empty() {
-        println!("{}", description_line);
-    }
-    println!(
-        "\x1b[34;1m╚{}\x1b[0m",
-        border_string.clone() + "╝\x1b[34;1m\x1b[0m"
-    );
-    println!(
-        "\x1b[34;1m╔{}\x1b[0m",
-        border_string.clone() + "╗\x1b[34;1m\x1b[0m"
-    );
-
-    // Prompt user for new tag, description, and language
-    print!("\x1b[1m\x1b[36m  Enter new tag (\x1b[1;33mleave blank to keep current, press 'Return'\x1b[0m\x1b[36m): \x1b[0m");
-    io::stdout().flush().unwrap();
-    let mut new_tag = String::new();
-    io::stdin().read_line(&mut new_tag).unwrap();
-    if !new_tag.trim().is_empty() {
-        snippet_to_edit.tag = new_tag.trim().to_string();
-    }
-
-    print!("\x1b[1m\x1b[36m  Enter new description (\x1b[1;33mleave blank to keep current, press 'Return'\x1b[0m\x1b[36m): \x1b[0m");
-    io::stdout().flush().unwrap();
-    let mut new_description = String::new();
-    io::stdin().read_line(&mut new_description).unwrap();
-    if !new_description.trim().is_empty() {
-        snippet_to_edit.description = Some(new_description.trim().to_string());
-    } else {
-        snippet_to_edit.description = None;
-    }
-
-    print!("\x1b[1m\x1b[36m  Enter new language (\x1b[1;33mleave blank to keep current, press 'Return'\x1b[0m\x1b[36m): \x1b[0m");
-    io::stdout().flush().unwrap();
-    let mut new_language = String::new();
-    io::stdin().read_line(&mut new_language).unwrap();
-    if !new_language.trim().is_empty() {
-        snippet_to_edit.language = Some(new_language.trim().to_string());
-    } else {
-        snippet_to_edit.language = None;
-    }
 
-    println!(
-        "\x1b[34;1m╚{}\x1b[0m",
-        border_string.clone() + "╝\x1b[34;1m\x1b[0m"
-    );
-    println!("\n  \x1b[33;1mCurrent Code:\x1b[0m\n");
-
-    // Print the current code with syntax highlighting
-    let highlighted_code = if let Some(lang) = &snippet_to_edit.language {
-        highlight_code_snippets(&snippet_to_edit.code, lang)
-    } else {
-        snippet_to_edit.code.clone()
-    };
-    println!("  {}", highlighted_code);
-
-    println!(
-        "\x1b[34;1m╔{}\x1b[0m",
-        border_string.clone() + "╗\x1b[34;1m\x1b[0m"
-    );
-    println!("\x1b[1;36m Enter your code snippet (press \x1b[33m'Return'\x1b[1;36m, then \x1b[33m'Ctrl+D'\x1b[1;36m to finish):\x1b[0m");
-    println!("\x1b[1;31m Note:\x1b[0m \x1b[1;33m'Arrow Keys'\x1b[0m \x1b[1;36mare not captured, use \x1b[1;33m'Backspace'\x1b[0m \x1b[1;36mto erase inputs\x1b[0m");
-    println!(
-        "\x1b[34;1m╚{}\x1b[0m",
-        border_string.clone() + "╝\x1b[34;1m\x1b[0m"
-    );
-    io::stdout().flush().unwrap();
-
-    // Read the new code snippet from the user input
-    let mut new_code = String::new();
-    io::stdin()
-        .read_to_string(&mut new_code)
-        .expect("failed to read snippet from input");
+        // If the Restore command is selected
+        Commands::Restore { id, version } => {
+            restore_version(ctx, data_file, *id, *version)?;
+            ctx.announce(&format!(
+                "\n\x1b[1;32mVersion {} of snippet {} has been restored, the replaced code is now version 1.\x1b[0m",
+                version, id
+            ));
+        }
 
-    // Update the snippet with the new code and save it
-    snippet_to_edit.code = new_code;
-    snippets.push(snippet_to_edit);
-    save_snippets_for_edit(snippets, file_path)?;
+        // If the Retag command is selected
+        Commands::Retag { from, to } => {
+            // An empty new tag would silently drop the tag, so refuse it
+            if to.trim().is_empty() || to.contains(',') {
+                return Err(CodevaultError::InvalidTag(to.clone()));
+            }
+            let updated = retag_snippets(ctx, data_file, from, to)?;
+            ctx.announce(&format!(
+                "\n\x1b[1;32mRetagged {} {} from '{}' to '{}'.\x1b[0m",
+                updated,
+                if updated == 1 { "snippet" } else { "snippets" },
+                from.trim(),
+                to.trim()
+            ));
+        }
 
-    Ok(())
-}
+        // If the Stats command is selected
+        Commands::Stats => {
+            ctx.announce("\n\x1b[38;5;201;1mCollection Statistics:\x1b[0m\n");
+            print_stats(ctx, data_file)?;
+        }
 
-// Function to save the updated list of snippets to a file
-fn save_snippets_for_edit(snippets: Vec<Snippet>, file_path: &str) -> Result<(), String> {
-    // Open the file with write, truncate, and create options
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(file_path)
-        .map_err(|err| format!("\x1b[1;33m opening file{}\x1b[0m", err))?;
-
-    // Serialize the snippets and write to the file
-    serde_json::to_writer_pretty(&mut file, &snippets)
-        .map_err(|err| format!(" serializing snippets: '\x1b[1;33m{}\x1b[0m'", err))?;
-    Ok(())
-}
+        // If the Tags command is selected
+        Commands::Tags => {
+            ctx.announce("\n\x1b[38;5;201;1mTags:\x1b[0m\n");
+            print_tags(ctx, data_file)?;
+        }
 
-// Function to copy a snippet based on its ID
-fn copy_code(file_path: &str, id: &Option<u32>) -> Result<Snippet, String> {
-    // Load snippets from the file
-    let snippets = load_snippets(file_path)?;
-
-    // Check if ID is provided
-    if id.is_none() {
-        let error_message = "missing snippet ID
-        \nPlease provide a snippet ID using the \x1b[1m\x1b[36m-i\x1b[0m or \x1b[1m\x1b[36m--id\x1b[0m flag.
-        \n\x1b[1m\x1b[32m\x1b[4mUsage:\x1b[0m \x1b[1m\x1b[36mcodevault copy\x1b[0m \x1b[1m\x1b[36m-i\x1b[0m \x1b[34m<ID>\x1b[0m
-        \n\x1b[1m\x1b[32m\x1b[4mExample:\x1b[0m \x1b[1m\x1b[36mcodevault copy\x1b[0m \x1b[1m\x1b[36m-i\x1b[0m \x1b[1m\x1b[34m22\x1b[0m
-        \nFor more information, try '\x1b[1m\x1b[36m--help\x1b[0m'".to_string();
-        return Err(error_message);
-    }
+        // If the Template command is selected, edit the templates in the config file
+        Commands::Template { action } => match action {
+            TemplateAction::Add {
+                name,
+                tag,
+                description,
+                language,
+            } => {
+                // Store the language the way capture would, so a typo fails now
+                let language = match language {
+                    Some(language) => {
+                        validate_language(language, &supported_languages)?;
+                        Some(canonical_language(language))
+                    }
+                    None => None,
+                };
+                let template = Template {
+                    tag: tag.clone(),
+                    description: description.clone(),
+                    language,
+                };
+                let config_path = config_path.ok_or(CodevaultError::NoConfigPath)?;
+                let replaced = add_template(config_path, name, &template)?;
+                ctx.announce(&format!(
+                    "\n\x1b[1;32mTemplate '{}' {} in '{}'.\x1b[0m",
+                    name,
+                    if replaced { "updated" } else { "saved" },
+                    config_path.display()
+                ));
+            }
+            TemplateAction::List => {
+                ctx.announce("\n\x1b[38;5;201;1mTemplates:\x1b[0m\n");
+                print_templates(ctx);
+            }
+            TemplateAction::Remove { name } => {
+                let config_path = config_path.ok_or(CodevaultError::NoConfigPath)?;
+                remove_template(config_path, name)?;
+                ctx.announce(&format!("\n\x1b[1;32mTemplate '{}' removed.\x1b[0m", name));
+            }
+        },
 
-    // Extract the ID from the option
-    let id = id.unwrap();
+        // If the Trash command is selected
+        Commands::Trash { .. } => list_trash(ctx, data_file)?,
 
-    // Find and return the snippet with the given ID
-    if let Some(snippet) = snippets.iter().find(|s| s.id == id) {
-        return Ok(snippet.clone());
-    }
+        // If the Tree command is selected
+        Commands::Tree => {
+            ctx.announce("\n\x1b[38;5;201;1mTag Tree:\x1b[0m\n");
+            print_tag_tree(ctx, data_file)?;
+        }
 
-    // Return an error if the snippet ID is not found
-    Err(format!(
-        " snippet ID '\x1b[1;33m{}\x1b[0m' does not exist in the collection",
-        id
-    ))
-}
+        // If the Undo command is selected
+        Commands::Undo => {
+            let ids = undo_delete(ctx, data_file)?;
+            ctx.announce(&format!(
+                "\n\x1b[1;32mRestored {} {} as ID {}.\x1b[0m",
+                ids.len(),
+                if ids.len() == 1 {
+                    "snippet"
+                } else {
+                    "snippets"
+                },
+                ids.iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
 
-// Function to delete snippets based on their IDs
-fn delete_snippet(file_path: &str, ids: &[u32]) -> Result<(), String> {
-    // Load the existing snippets from the file
-    let mut snippets = load_snippets(file_path)?;
-
-    // Vector to hold IDs that do not exist in the current snippets
-    let mut non_existent_ids: Vec<u32> = Vec::new();
-    // Check which of the provided IDs do not exist
-    for id in ids {
-        if !snippets.iter().any(|s| s.id == *id) {
-            non_existent_ids.push(*id);
+        // If the Unpin command is selected
+        Commands::Unpin { id } => {
+            set_pinned(ctx, data_file, *id, false)?;
+            ctx.announce(&format!(
+                "\n\x1b[1;32mSnippet {} has been unpinned.\x1b[0m",
+                id
+            ));
         }
-    }
 
-    // If there are non-existent IDs, return an error with their details
-    if !non_existent_ids.is_empty() {
-        let ids_str = non_existent_ids
-            .iter()
-            .map(|id| id.to_string())
-            .collect::<Vec<_>>()
-            .join(", ");
-        return Err(format!(
-            " snippet ID '\x1b[1;33m{}\x1b[0m' does not exist in the collection",
-            ids_str
-        ));
-    }
+        // If the Verify command is selected
+        Commands::Verify { fix } => {
+            let snippets = snippets_with_unknown_language(ctx, data_file)?;
+            if snippets.is_empty() {
+                ctx.announce("\n\x1b[1;32mEvery snippet's language is supported.\x1b[0m");
+                return Ok(());
+            }
 
-    // Prepare a string of IDs for confirmation prompt
-    let ids_str = ids
-        .iter()
-        .map(|id| id.to_string())
-        .collect::<Vec<_>>()
-        .join(", ");
-    let mut plural = "snippet";
-    if ids.len() > 1 {
-        plural = "snippets";
-    }
-    println!("\n\x1b[38;5;201;1mDelete snippet:\x1b[0m\n");
-    // Prompt user for confirmation
-    print!("\x1b[1m\x1b[36mAre you sure you want to permanently delete {} {} ? Please confirm (\x1b[33my/N\x1b[36m): \x1b[0m", plural, ids_str);
-    io::stdout().flush().unwrap();
-
-    // Read user input for confirmation
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-    if input.trim().to_lowercase() != "y" {
-        print!("\n\x1b[91mSnippet deletion cancelled\x1b[0m\n");
-        return Ok(());
-    }
+            // The new languages are typed in, so fixing needs someone at the terminal
+            if *fix && (!io::stdin().is_terminal() || ctx.quiet) {
+                return Err(CodevaultError::MissingArgument("the new languages"));
+            }
 
-    // Remove snippets with the specified IDs
-    let mut deleted_count = 0;
-    for id in ids {
-        if let Some(index) = snippets.iter().position(|s| s.id == *id) {
-            snippets.remove(index);
-            deleted_count += 1;
-        }
-    }
+            for snippet in &snippets {
+                let language = snippet.language.as_deref().unwrap_or_default();
+
+                // Report the language the way capture would reject it, with the closest supported name
+                let problem = validate_language(language, &supported_languages)
+                    .err()
+                    .map(|err| err.to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{}",
+                    paint(&format!(
+                        "\x1b[1;33mSnippet {} is shown as plain text:\x1b[0m {}",
+                        snippet.id, problem
+                    ))
+                );
+                if !*fix {
+                    continue;
+                }
 
-    // Save the remaining snippets back to the file
-    if deleted_count > 0 {
-        save_snippets_for_edit(snippets, file_path)?;
-        println!("\n\x1b[32mdeleted successfully!\x1b[0m");
-    }
+                // Ask until the answer is a supported language or blank
+                loop {
+                    let answer = prompt(&format!(
+                        "{}{}{}",
+                        style::prompt("Enter the new language ("),
+                        style::label("leave blank to clear it"),
+                        style::prompt("): ")
+                    ));
+                    if answer.is_empty() {
+                        set_language(ctx, data_file, snippet.id, None)?;
+                        break;
+                    }
+                    match validate_language(&answer, &supported_languages) {
+                        Ok(()) => {
+                            set_language(ctx, data_file, snippet.id, Some(&answer))?;
+                            break;
+                        }
+                        Err(err) => print_error(&err),
+                    }
+                }
+            }
+        }
 
-    Ok(())
-}
+        // If the View command is selected
+        Commands::View {
+            id,
+            tag,
+            language,
+            keyword,
+            fuzzy,
+            summary,
+            fields,
+            sort,
+            reverse,
+            line_numbers,
+            no_wrap,
+            tab_width,
+            pinned,
+            exact,
+            render,
+            code_only,
+            grep,
+            context,
+            limit,
+            offset,
+            count,
+            no_pager,
+        } => {
+            // View and print snippets based on the provided filters (ID, tag, language, keyword, or summary)
+            let filter = ViewFilter {
+                id: *id,
+                tag: tag.clone(),
+                language: language.clone(),
+                keyword: keyword.clone(),
+                fuzzy: fuzzy.clone(),
+                pinned: *pinned,
+                exact: *exact,
+                sort: *sort,
+                reverse: *reverse,
+            };
 
-// Function to export snippets based on filters
-fn export_snippets(
-    file_path: &str,
-    id: &Option<u32>,
-    tag: &Option<String>,
-    language: &Option<String>,
-    export_path: &Option<PathBuf>,
-) -> Result<(), String> {
-    // Load the existing snippets from the file
-    let snippets = load_snippets(file_path)?;
-
-    // Start with all snippets and apply filters
-    let mut filtered_snippets: Vec<Snippet> = snippets.clone();
-
-    // Filter by snippet ID if provided
-    if let Some(id) = id {
-        filtered_snippets = filtered_snippets
-            .into_iter()
-            .filter(|s| s.id == *id)
-            .collect::<Vec<_>>();
-    }
+            // With --grep, only snippets with a matching code line are shown
+            let pattern = grep.as_deref().map(compile_pattern).transpose()?;
+            let has_match =
+                |snippet: &Snippet| pattern.as_ref().is_none_or(|p| p.is_match(&snippet.code));
+
+            // Only print the number of matches, an unknown ID simply counts as no match
+            if *count {
+                let filter = ViewFilter { id: None, ..filter };
+                let mut snippets = view_snippets(ctx, data_file, &filter)?;
+                snippets.retain(has_match);
+                println!(
+                    "{}",
+                    snippets
+                        .iter()
+                        .filter(|s| id.is_none_or(|id| s.id == id))
+                        .count()
+                );
+                return Ok(());
+            }
 
-    // Filter by tag if provided
-    if let Some(tag) = tag {
-        let tags: Vec<&str> = tag.split(',').map(|s| s.trim()).collect();
-        filtered_snippets = filtered_snippets
-            .into_iter()
-            .filter(|s| {
-                tags.iter()
-                    .any(|t| s.tag.to_lowercase().contains(&t.to_lowercase()))
-            })
-            .collect::<Vec<_>>();
-    }
+            let mut snippets = view_snippets(ctx, data_file, &filter)?;
+            snippets.retain(has_match);
 
-    // Filter by language if provided
-    if let Some(lang) = language {
-        let langs: Vec<&str> = lang.split(',').map(|s| s.trim()).collect();
-        filtered_snippets = filtered_snippets
-            .into_iter()
-            .filter(|s| {
-                langs.iter().any(|l| {
-                    s.language
-                        .as_ref()
-                        .map(|lang| lang.to_lowercase().contains(&l.to_lowercase()))
-                        .unwrap_or(false)
-                })
-            })
-            .collect::<Vec<_>>();
-    }
+            // Collect the whole listing first, so it can be paged when it is taller than the terminal
+            let mut output = if ctx.quiet || *code_only || pattern.is_some() {
+                String::new()
+            } else {
+                paint(spaced("\n\x1b[38;5;201;1mSnippets Collection:\x1b[0m\n")) + "\n"
+            };
 
-    // Check if any snippets match the filter criteria
-    if filtered_snippets.is_empty() {
-        if let Some(id) = id {
-            return Err(format!(
-                " snippet '\x1b[1;33m{}\x1b[0m' ID does not exist in the collection",
-                id
-            ));
-        } else if let Some(tag) = tag {
-            let tags: Vec<&str> = tag.split(',').map(|s| s.trim()).collect();
-            if let Some(lang) = language {
-                let langs: Vec<&str> = lang.split(',').map(|s| s.trim()).collect();
-                let matching_html_count = snippets
-                    .iter()
-                    .filter(|s| {
-                        s.language
+            // Slice the filtered and sorted snippets down to the requested page
+            let total = snippets.len();
+            let start = (*offset).min(total);
+            let end = limit.map_or(total, |limit| (start + limit).min(total));
+
+            // Show every line of the snippet box unless specific fields are chosen
+            let display = DisplayOptions {
+                line_numbers: *line_numbers,
+                wrap: !*no_wrap,
+                tab_width: *tab_width,
+                render_markdown: *render,
+                // Point out where the keywords occur in the code
+                highlight_terms: keyword.as_deref().map(parse_tags).unwrap_or_default(),
+                fields: if fields.is_empty() {
+                    SnippetField::DEFAULT.to_vec()
+                } else {
+                    fields.clone()
+                },
+                theme: ctx.config.theme.clone(),
+            };
+            let matcher = SkimMatcherV2::default();
+
+            // Highlighting is the slow part, so the snippets are rendered in parallel and then
+            // joined back in their listed order
+            let rendered: Vec<String> = snippets[start..end]
+                .par_iter()
+                .enumerate()
+                .map(|(index, snippet)| {
+                    if let Some(pattern) = &pattern {
+                        // Only the matching lines, grep style
+                        render_grep(snippet, pattern, *context, &display).unwrap_or_default()
+                    } else if *code_only {
+                        // Only the code, with a thin rule between snippets
+                        let separator = if index > 0 {
+                            code_only_separator()
+                        } else {
+                            String::new()
+                        };
+                        separator + &render_code_only(snippet, &display)
+                    } else if *summary {
+                        // Show how well each snippet matched a fuzzy search
+                        let score = fuzzy
                             .as_ref()
-                            .map(|l| l.to_lowercase().contains(&"html".to_lowercase()))
-                            .unwrap_or(false)
-                    })
-                    .count();
-
-                return Err(format!(
-                    "\x1b[1m\x1b[93m'{}'\x1b[0m\x1b[1m matching snippets found, but no snippets match both \x1b[93m'{}'\x1b[0m\x1b[1m tags and \x1b[93m'{}'\x1b[0m\x1b[1m language.\nPlease check your criteria. Both should match in order to export.\x1b[0m",
-                    matching_html_count,
-                    tags.join(", "),
-                    langs.join(", ")
-                ));
-            } else {
-                return Err(format!(
-                    " snippet with '{}' tags does not exist in the collection",
-                    tags.join(", ")
-                ));
+                            .and_then(|query| snippet.fuzzy_score(&matcher, query));
+                        render_summary(snippet, score, &ctx.config.theme)
+                    } else {
+                        render_snippet(snippet, &display)
+                    }
+                })
+                .collect();
+            output.push_str(&rendered.concat());
+
+            // Tell the user how to reach the snippets beyond this page
+            if end < total && !ctx.quiet {
+                output.push_str(
+                    &(paint(&format!(
+                        "\x1b[1;36mshowing {}–{} of {}; use \x1b[1;33m--offset {}\x1b[1;36m for more\x1b[0m",
+                        start + 1,
+                        end,
+                        total,
+                        end
+                    )) + "\n"),
+                );
             }
-        } else if let Some(lang) = language {
-            let langs: Vec<&str> = lang.split(',').map(|s| s.trim()).collect();
-            return Err(format!(
-                " snippet with '{}' language does not exist in the collection",
-                langs.join(", ")
-            ));
-        }
-    }
 
-    // Confirm export if more than one snippet is being exported
-    if filtered_snippets.len() > 1 {
-        println!("\n\x1b[38;5;201;1mExport Snippets:\x1b[0m\n");
-        print!("\x1b[1m\x1b[36mExporting {} snippets in language-specific formats. Are you sure you want to continue? (\x1b[33my\x1b[36m/\x1b[33mN\x1b[36m): \x1b[0m", filtered_snippets.len());
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-
-        if input.trim().to_lowercase() != "y" {
-            println!("\x1b[1m\x1b[91m\nSnippet export cancelled\x1b[0m\x1b[0m");
-            return Ok(());
+            page_output(&output, !*no_pager);
         }
     }
 
-    // Determine export directory
-    let export_dir = match export_path {
-        Some(path) => path.clone(),
-        None => {
-            println!("\x1b[1m\x1b[36mNo export path specified. Exporting snippets to the default 'snippet_exports' directory. Please wait...\x1b[0m");
-            PathBuf::from("snippet_exports")
-        }
-    };
+    Ok(())
+}
 
-    // Create the export directory if it does not exist
-    std::fs::create_dir_all(&export_dir)
-        .map_err(|err| format!("\x1b[31merror:\x1b[0m creating directory: {}\x1b[0m", err))?;
-
-    // Export each snippet to a file
-    for snippet in filtered_snippets {
-        let _lowercase_lang = snippet.language.as_ref().map(|lang| lang.to_lowercase());
-
-        // Determine file extension based on snippet language
-        let extension = match snippet.language.as_deref() {
-            Some("AppleScript") => "applescript",
-            Some("ASP") => "asp",
-            Some("Batch File") => "bat",
-            Some("BibTeX") => "bib",
-            Some("Bourne Again Shell (bash)") => "sh",
-            Some("C") => "c",
-            Some("C#") => "cs",
-            Some("C++") => "cpp",
-            Some("Cargo Build Results") => "log",
-            Some("Clojure") => "clj",
-            Some("commands-builtin-shell-bash") => "sh",
-            Some("CSS") => "css",
-            Some("D") => "d",
-            Some("Diff") => "diff",
-            Some("Erlang") => "erl",
-            Some("Go") => "go",
-            Some("Graphviz (DOT)") => "dot",
-            Some("Groovy") => "groovy",
-            Some("Haml") => "haml",
-            Some("Haskell") => "hs",
-            Some("HTML") => "html",
-            Some("Java") => "java",
-            Some("Java Properties") => "properties",
-            Some("JavaScript") => "js",
-            Some("JSON") => "json",
-            Some("LaTeX") => "tex",
-            Some("LaTeX Log") => "log",
-            Some("Lisp") => "lisp",
-            Some("Lua") => "lua",
-            Some("Make Output") => "mak",
-            Some("Makefile") => "mak",
-            Some("Markdown") => "md",
-            Some("MATLAB") => "m",
-            Some("MultiMarkdown") => "mmd",
-            Some("NAnt Build File") => "build",
-            Some("Objective-C") => "m",
-            Some("Objective-C++") => "mm",
-            Some("OCaml") => "ml",
-            Some("OCamllex") => "mll",
-            Some("OCamlyacc") => "mly",
-            Some("Pascal") => "pas",
-            Some("Perl") => "pl",
-            Some("PHP") => "php",
-            Some("Python") => "py",
-            Some("R") => "R",
-            Some("R Console") => "Rout",
-            Some("Rd (R Documentation)") => "Rd",
-            Some("Regular Expression") => "regex",
-            Some("Regular Expressions (Javascript)") => "js",
-            Some("Regular Expressions (Python)") => "py",
-            Some("reStructuredText") => "rst",
-            Some("Ruby") => "rb",
-            Some("Ruby on Rails") => "rb",
-            Some("Rust") => "rs",
-            Some("Scala") => "scala",
-            Some("Shell-Unix-Generic") => "sh",
-            Some("SQL") => "sql",
-            Some("Tcl") => "tcl",
-            Some("TeX") => "tex",
-            Some("Textile") => "textile",
-            Some("XML") => "xml",
-            Some("YAML") => "yaml",
-            _ => "txt", // Default extension for unknown languages
-        };
-
-        // Create the filename for the exported snippet
-        let filename = format!("{}/{}.{}", export_dir.display(), snippet.id, extension);
-
-        // Check if the file already exists
-        if std::fs::metadata(&filename).is_ok() {
-            println!(
-                "\n\x1b[1m\x1b[93mThe file has been already exported and is located at '{}'.\x1b[0m\x1b[0m",
-                filename
-            );
-            continue;
+// Function to render an error for the terminal, highlighting the values it refers to
+fn render_error(err: &CodevaultError) -> String {
+    let highlight = |value: &dyn std::fmt::Display| format!("'{}'", style::label(value));
+    match err {
+        CodevaultError::NotFound(id) => format!(
+            "snippet ID {} does not exist in the collection",
+            highlight(id)
+        ),
+        CodevaultError::InvalidId(id) => format!("invalid snippet ID {}", highlight(id)),
+        CodevaultError::MissingId => format!(
+            "missing snippet ID\n\nPlease provide a snippet ID using the {} or {} flag.\n\n{} {} {} {}\n\n{} {} {} {}\n\nFor more information, try '{}'",
+            style::prompt("-i"),
+            style::prompt("--id"),
+            style::header("Usage:"),
+            style::prompt("codevault copy"),
+            style::prompt("-i"),
+            style::value("<ID>"),
+            style::header("Example:"),
+            style::prompt("codevault copy"),
+            style::prompt("-i"),
+            style::value(22),
+            style::prompt("--help")
+        ),
+        CodevaultError::MissingSelector => format!(
+            "missing snippet ID or tag\n\nTo edit a snippet, use its {} or {}.",
+            style::label("ID"),
+            style::label("tag")
+        ),
+        CodevaultError::NoMatch(selectors) => format!(
+            "no snippets match {} in the collection",
+            highlight(selectors)
+        ),
+        CodevaultError::UnsupportedLanguage {
+            language,
+            suggestion,
+        } => format!(
+            "unsupported language {}{}\n\nRun '{}' to see the supported languages.",
+            highlight(language),
+            suggestion
+                .as_ref()
+                .map(|name| format!(", did you mean '{}'?", style::language(name)))
+                .unwrap_or_default(),
+            style::prompt("codevault languages")
+        ),
+        CodevaultError::Deserialize { path, source } => {
+            format!("reading {}: {}", highlight(path), source)
         }
-
-        // Create the file and write the snippet code to it
-        let file = File::create(&filename).map_err(|err| {
-            format!(
-                "\x1b[31merror:\x1b[0m  creating file {}: {}\x1b[0m",
-                filename, err
-            )
-        })?;
-        let mut writer = BufWriter::new(file);
-
-        write!(writer, "{}", snippet.code).map_err(|err| {
-            format!(
-                "\x1b[31merror:\x1b[0m  writing to file {}: {}\x1b[0m",
-                filename, err
-            )
-        })?;
-
-        // Confirm successful export
-        println!(
-            "\x1b[1;32m\nSuccessfully exported snippet to file '{}'.\x1b[0m",
-            filename
-        );
+        _ => err.to_string(),
     }
+}
 
-    Ok(())
+// Function to print an error in the CLI's colors, on stderr so it never mixes with piped output
+fn print_error(err: &CodevaultError) {
+    eprintln!("{} {}", style::error("error:"), render_error(err));
 }
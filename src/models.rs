@@ -1,8 +1,89 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use chrono::{DateTime, Local};
 use clap::builder::styling;
 use clap::builder::Styles;
+use clap::ValueEnum;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
 
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use unicode_width::UnicodeWidthChar;
+
+use crate::error::CodevaultError;
+
+// Global switch deciding whether ANSI color codes are written to the terminal
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// Global switch dropping the blank lines around headers, banners and boxes
+static COMPACT: AtomicBool = AtomicBool::new(false);
+
+// Global switch showing timestamps relative to now, e.g. "3 days ago"
+static RELATIVE_TIME: AtomicBool = AtomicBool::new(false);
+
+// Theme used for syntax highlighting when the config file doesn't pick one
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+// Per-user defaults read from the TOML config file, every field is optional
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // syntect theme used to highlight code, e.g. "Solarized (dark)"
+    pub theme: String,
+    // Snippets file used when --data and CODEVAULT_DATA are not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_path: Option<PathBuf>,
+    // Directory exports go to when --path and CODEVAULT_EXPORT_DIR are not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_dir: Option<PathBuf>,
+    // Set to false to never color the output, like --no-color
+    pub color: bool,
+    // Named sets of capture defaults, chosen with `capture --template <name>`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, Template>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            theme: DEFAULT_THEME.to_string(),
+            data_path: None,
+            export_dir: None,
+            color: true,
+            templates: BTreeMap::new(),
+        }
+    }
+}
+
+// Values filled in by `capture --template <name>` for the options left out on the command line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Template {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+// Encrypted vaults start with this header, followed by the salt, the nonce and the ciphertext
+const ENCRYPTED_HEADER: &[u8] = b"CODEVAULT-AES256GCM-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
 
 pub fn vault_styling() -> Styles {
     styling::Styles::styled()
@@ -12,17 +93,1128 @@ pub fn vault_styling() -> Styles {
         .placeholder(styling::AnsiColor::Blue.on_default())
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Snippet {
-    pub tag: String,
+    #[serde(deserialize_with = "deserialize_tags")]
+    pub tag: Vec<String>,
     pub description: Option<String>,
     pub code: String,
-    pub timestamp: String,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub timestamp: DateTime<Local>,
+    pub language: Option<String>,
+    pub id: u32,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub modified: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub copy_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<SnippetVersion>,
+    // syntect theme used for this snippet instead of the configured one, e.g. for diffs or logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+// Most previous versions of its code a snippet keeps, the oldest are dropped first
+pub const HISTORY_LIMIT: usize = 10;
+
+// Code a snippet had before it was edited, kept so the edit can be rolled back
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnippetVersion {
+    pub code: String,
+    // When this code was saved, i.e. when the snippet was captured or last modified before the edit
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub timestamp: DateTime<Local>,
+}
+
+// Outcome of an import, the skipped entries are only possible with --lenient
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    // New IDs of the imported snippets, in the order they were read
+    pub imported: Vec<u32>,
+    // Position in the input and reason of every entry that was left out
+    pub skipped: Vec<(usize, String)>,
+}
+
+// A deleted snippet kept in the trash file so it can be restored with `undo`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashEntry {
+    pub batch: u32,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub deleted: DateTime<Local>,
+    pub snippet: Snippet,
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+// Options controlling how a snippet is rendered inside its box
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    pub line_numbers: bool,
+    pub wrap: bool,
+    pub fields: Vec<SnippetField>,
+    pub tab_width: usize,
+    pub render_markdown: bool,
+    // Terms shown in reverse video wherever they occur in the code, e.g. the keywords searched for
+    pub highlight_terms: Vec<String>,
+    // Theme for snippets that don't pick their own, usually the one from the config file
+    pub theme: String,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            line_numbers: false,
+            wrap: true,
+            fields: SnippetField::DEFAULT.to_vec(),
+            tab_width: 4,
+            render_markdown: false,
+            highlight_terms: Vec::new(),
+            theme: DEFAULT_THEME.to_string(),
+        }
+    }
+}
+
+// Lines of a snippet box that can be chosen with `view --fields`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnippetField {
+    Id,
+    Tag,
+    Language,
+    Created,
+    Modified,
+    Description,
+    Source,
+    Code,
+}
+
+impl SnippetField {
+    // Every line in the order it is shown when no fields are chosen
+    pub const DEFAULT: [SnippetField; 8] = [
+        SnippetField::Id,
+        SnippetField::Tag,
+        SnippetField::Language,
+        SnippetField::Created,
+        SnippetField::Modified,
+        SnippetField::Description,
+        SnippetField::Source,
+        SnippetField::Code,
+    ];
+}
+
+// How an edit ended, so the caller can tell the user what happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    // The changes were saved, or shown on a dry run
+    Saved,
+    // The snippet came out identical, so nothing was written
+    Unchanged,
+    // The user typed ':q' at a prompt
+    Cancelled,
+}
+
+// Fields to update directly when editing, without going through the interactive prompts
+#[derive(Debug, Default, Clone)]
+pub struct EditFields {
+    pub language: Option<String>,
+    pub description: Option<String>,
+    // An empty theme goes back to the configured one
+    pub theme: Option<String>,
+}
+
+impl EditFields {
+    // Check whether no field was given, in which case the interactive edit is used
+    pub fn is_empty(&self) -> bool {
+        self.language.is_none() && self.description.is_none() && self.theme.is_none()
+    }
+}
+
+// Filters and ordering applied when viewing snippets
+#[derive(Debug, Default, Clone)]
+pub struct ViewFilter {
+    pub id: Option<u32>,
+    pub tag: Option<String>,
     pub language: Option<String>,
+    pub keyword: Option<String>,
+    pub fuzzy: Option<String>,
+    pub pinned: bool,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub exact: bool,
+}
+
+// Output formats available to the export command
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Files,
+    Markdown,
+    // One standalone, highlighted HTML page per snippet
+    Html,
+}
+
+// Line endings the export command writes the code with
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    // Write the code byte for byte as it was captured
+    Keep,
+}
+
+impl LineEnding {
+    // Convert every line ending of the text and drop a leading byte order mark, unless keeping it as is
+    pub fn apply(self, text: &str) -> String {
+        let unix = || text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        match self {
+            LineEnding::Lf => unix(),
+            LineEnding::Crlf => unix().replace('\n', "\r\n"),
+            LineEnding::Keep => text.to_string(),
+        }
+    }
+}
+
+// Where and how the export command writes the selected snippets
+#[derive(Debug, Default, Clone)]
+pub struct ExportOptions {
+    pub path: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub format: ExportFormat,
+    pub force: bool,
+    pub plain: bool,
+    pub stdout: bool,
+    pub line_endings: LineEnding,
+    pub verbose: bool,
+    pub exact: bool,
+    // Only export the snippets added or modified since the previous export made with it
+    pub since_last: bool,
+}
+
+// Orderings available to the view command
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Id,
+    Created,
+    Modified,
+    Tag,
+    Language,
+    Copies,
+}
+
+// Canonical spellings `normalize-tags` can rewrite the stored tags to
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagCase {
+    // "web api" stays "web api", "Rust" becomes "rust"
+    #[default]
+    Lower,
+    // "web api" becomes "Web Api", "RUST" becomes "Rust"
+    Title,
+}
+
+impl TagCase {
+    // Spell a tag in this case, words are separated by spaces, dashes or underscores
+    pub fn apply(self, tag: &str) -> String {
+        match self {
+            TagCase::Lower => tag.to_lowercase(),
+            TagCase::Title => {
+                let mut title = String::with_capacity(tag.len());
+                let mut word_start = true;
+                for ch in tag.chars() {
+                    if word_start {
+                        title.extend(ch.to_uppercase());
+                    } else {
+                        title.extend(ch.to_lowercase());
+                    }
+                    word_start = ch.is_whitespace() || ch == '-' || ch == '_';
+                }
+                title
+            }
+        }
+    }
+}
+
+impl Snippet {
+    // Keep the code the snippet had before an edit in its history, newest version first
+    pub fn remember_version(&mut self, previous: &Snippet) {
+        if previous.code == self.code {
+            return;
+        }
+        self.history.insert(
+            0,
+            SnippetVersion {
+                code: previous.code.clone(),
+                timestamp: previous.modified.unwrap_or(previous.timestamp),
+            },
+        );
+        self.history.truncate(HISTORY_LIMIT);
+    }
+
+    // Check whether any of the snippet's tags contains the query, ignoring case
+    pub fn has_tag(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.tag
+            .iter()
+            .any(|tag| tag.to_lowercase().contains(&query))
+    }
+
+    // Replace the tag equal to `from` (ignoring case) with `to`, returning whether anything changed.
+    // Both are trimmed, so " new" is stored as "new".
+    pub fn retag(&mut self, from: &str, to: &str) -> bool {
+        let from = from.trim().to_lowercase();
+        let to = to.trim();
+        let Some(index) = self.tag.iter().position(|tag| tag.to_lowercase() == from) else {
+            return false;
+        };
+
+        // Drop the old entry instead of duplicating a tag the snippet already has
+        let lowered = to.to_lowercase();
+        let duplicate = self
+            .tag
+            .iter()
+            .enumerate()
+            .any(|(other, tag)| other != index && tag.to_lowercase() == lowered);
+        if duplicate {
+            self.tag.remove(index);
+        } else {
+            self.tag[index] = to.to_string();
+        }
+        true
+    }
+
+    // Rewrite every tag in the given case, dropping tags that turn out to be the same.
+    // Returns whether any tag changed.
+    pub fn normalize_tags(&mut self, case: TagCase) -> bool {
+        let mut normalized: Vec<String> = Vec::with_capacity(self.tag.len());
+        for tag in &self.tag {
+            let tag = case.apply(tag);
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+        if normalized == self.tag {
+            return false;
+        }
+        self.tag = normalized;
+        true
+    }
+
+    // Score how well the query fuzzily matches the tags, the description or a line of code,
+    // taking the best field so one long field can't drown out the others
+    pub fn fuzzy_score(&self, matcher: &SkimMatcherV2, query: &str) -> Option<i64> {
+        let tags = self.tags_display();
+        std::iter::once(tags.as_str())
+            .chain(self.description.as_deref())
+            .chain(self.code.lines())
+            .filter_map(|field| matcher.fuzzy_match(field, query))
+            .max()
+    }
+
+    // Check whether any tag matches one of the comma-separated tag queries, ignoring case. Tags
+    // are compared by their '/' separated parts, so "web" matches "web" and the tags nested below
+    // it like "web/frontend/react", but not "webpack". With `exact` set the whole tag has to
+    // match, leaving out the nested tags too.
+    pub fn matches_tags(&self, tags: &str, exact: bool) -> bool {
+        tags.split(',').any(|query| {
+            let query: Vec<String> = tag_path(query).into_iter().map(str::to_lowercase).collect();
+            !query.is_empty()
+                && self.tag.iter().any(|tag| {
+                    let path = tag_path(tag);
+                    let depth_matches = if exact {
+                        path.len() == query.len()
+                    } else {
+                        path.len() >= query.len()
+                    };
+                    depth_matches
+                        && path
+                            .iter()
+                            .zip(&query)
+                            .all(|(part, query)| part.to_lowercase() == *query)
+                })
+        })
+    }
+
+    // Check whether the language contains one of the comma-separated language queries, ignoring case
+    // Languages resolving to the same syntax match exactly, so "py" finds snippets stored as "Python",
+    // with `exact` set other queries have to equal the stored name instead of being part of it
+    pub fn matches_languages(&self, languages: &str, exact: bool) -> bool {
+        let Some(language) = &self.language else {
+            return false;
+        };
+        let resolved = resolve_language(language).map(|syntax| &syntax.name);
+        let language = language.to_lowercase();
+        languages
+            .split(',')
+            .map(|l| l.trim())
+            .any(|l| match (resolved, resolve_language(l)) {
+                (Some(stored), Some(query)) => *stored == query.name,
+                _ if exact => language == l.to_lowercase(),
+                _ => language.contains(&l.to_lowercase()),
+            })
+    }
+
+    // Hash the trimmed code, so snippets differing only in surrounding whitespace compare equal
+    pub fn code_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.code.trim().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Join the snippet's tags into a single readable line
+    pub fn tags_display(&self) -> String {
+        self.tag.join(", ")
+    }
+}
+
+// The fields `capture --append-only` reads from each record of the data file, the others are
+// skipped without being kept
+#[derive(Deserialize)]
+pub struct AppendRecord {
     pub id: u32,
+    pub code: String,
+}
+
+// Tags are stored as a list, older vaults stored them as a single string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagField {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+fn deserialize_tags<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match TagField::deserialize(deserializer)? {
+        TagField::Single(tag) => parse_tags(&tag),
+        TagField::Multiple(tags) => tags,
+    })
+}
+
+// Split a comma-separated list of tags, dropping empty entries
+pub fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .collect()
+}
+
+// Turn text into a single line comment in the given language, so it can sit between lines of code.
+// Languages without a known comment syntax get C-style comments, plain text gets a plain line.
+pub fn line_comment(language: Option<&str>, text: &str) -> String {
+    let Some(language) = language else {
+        return format!("--- {} ---", text);
+    };
+    let name = resolve_language(language)
+        .map(|syntax| syntax.name.clone())
+        .unwrap_or_else(|| language.to_string());
+    match name.as_str() {
+        "Python"
+        | "Ruby"
+        | "Perl"
+        | "R"
+        | "YAML"
+        | "Makefile"
+        | "Bourne Again Shell (bash)"
+        | "Shell-Unix-Generic"
+        | "TOML"
+        | "Tcl" => format!("# {}", text),
+        "SQL" | "Haskell" | "Lua" | "Ada" => format!("-- {}", text),
+        "Erlang" | "LaTeX" | "TeX" | "MATLAB" => format!("% {}", text),
+        "Lisp" | "Clojure" => format!("; {}", text),
+        "Batch File" => format!("REM {}", text),
+        "HTML" | "XML" | "Markdown" | "ASP" | "HTML (ASP)" => format!("<!-- {} -->", text),
+        "CSS" => format!("/* {} */", text),
+        _ => format!("// {}", text),
+    }
+}
+
+// Parse comma-separated snippet IDs as typed on the command line, e.g. "3, 7"
+pub fn parse_ids(input: &str) -> Result<Vec<u32>, CodevaultError> {
+    input
+        .split(',')
+        .map(|id| {
+            id.trim()
+                .parse::<u32>()
+                .map_err(|_| CodevaultError::InvalidId(id.trim().to_string()))
+        })
+        .collect()
+}
+
+// Split a nested tag like "web/frontend/react" into its parts, ignoring blanks around the slashes
+pub fn tag_path(tag: &str) -> Vec<&str> {
+    tag.split('/')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+// A level of the tag hierarchy, tags like "web/frontend/react" nest by their '/' separated parts
+#[derive(Debug, Default)]
+pub struct TagTree {
+    // Number of snippets with a tag at or below this level
+    pub count: usize,
+    pub children: BTreeMap<String, TagTree>,
+}
+
+impl TagTree {
+    // Build the hierarchy of all tags, the root counts every snippet
+    pub fn from_snippets(snippets: &[Snippet]) -> TagTree {
+        let mut root = TagTree {
+            count: snippets.len(),
+            ..TagTree::default()
+        };
+        for snippet in snippets {
+            // A snippet is counted once per level, even when several of its tags lead through it
+            let paths: BTreeSet<Vec<&str>> = snippet
+                .tag
+                .iter()
+                .flat_map(|tag| {
+                    let path = tag_path(tag);
+                    (1..=path.len()).map(move |depth| path[..depth].to_vec())
+                })
+                .collect();
+            for path in paths {
+                let node = path.iter().fold(&mut root, |node, part| {
+                    node.children.entry(part.to_string()).or_default()
+                });
+                node.count += 1;
+            }
+        }
+        root
+    }
+}
+
+// Parse an RFC 3339 timestamp, or one stored by older versions with `Local::now().to_string()`
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .or_else(|_| DateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f %:z"))
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+// Timestamps are saved as RFC 3339, older vaults are migrated when they are read
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<DateTime<Local>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timestamp = String::deserialize(deserializer)?;
+    parse_timestamp(&timestamp)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp '{}'", timestamp)))
+}
+
+fn deserialize_optional_timestamp<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<Local>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(timestamp) => parse_timestamp(&timestamp)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp '{}'", timestamp))),
+        None => Ok(None),
+    }
+}
+
+// Format a timestamp the same way everywhere it is shown, relative to now with --relative
+pub fn format_timestamp(timestamp: &DateTime<Local>) -> String {
+    if RELATIVE_TIME.load(Ordering::Relaxed) {
+        relative_timestamp(timestamp, &Local::now())
+    } else {
+        timestamp.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+// Describe how long before `now` a timestamp is, in its largest whole unit, e.g. "3 days ago"
+pub fn relative_timestamp(timestamp: &DateTime<Local>, now: &DateTime<Local>) -> String {
+    let elapsed = now.signed_duration_since(timestamp);
+    let days = elapsed.num_days();
+    let (count, unit) = if elapsed.num_minutes() < 1 {
+        // Also covers timestamps slightly ahead of this machine's clock
+        return "just now".to_string();
+    } else if elapsed.num_hours() < 1 {
+        (elapsed.num_minutes(), "minute")
+    } else if days < 1 {
+        (elapsed.num_hours(), "hour")
+    } else if days < 30 {
+        (days, "day")
+    } else if days < 365 {
+        (days / 30, "month")
+    } else {
+        (days / 365, "year")
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
 }
 
 pub fn strip_ansi_codes(input: &str) -> String {
-    let re = Regex::new(r"\x1B\[[0-9;]*[a-zA-Z]").unwrap();
+    static ANSI_CODES: OnceLock<Regex> = OnceLock::new();
+    let re = ANSI_CODES.get_or_init(|| Regex::new(r"\x1B\[[0-9;]*[a-zA-Z]").unwrap());
     re.replace_all(input, "").to_string()
 }
+
+// An ANSI escape sequence at the start of the text, compiled once since it runs for every code line
+fn leading_ansi_code() -> &'static Regex {
+    static LEADING_ANSI_CODE: OnceLock<Regex> = OnceLock::new();
+    LEADING_ANSI_CODE.get_or_init(|| Regex::new(r"^\x1B\[[0-9;]*[a-zA-Z]").unwrap())
+}
+
+// The default syntaxes, loaded once on first use
+pub fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+// The default themes, loaded once on first use
+pub fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+// Resolve a language typed by the user to its syntax, ignoring case and accepting common aliases
+// like "cpp", "py" or "rs" as well as any file extension known to syntect
+pub fn resolve_language(input: &str) -> Option<&'static SyntaxReference> {
+    let ps = syntax_set();
+    let input = input.trim().to_lowercase();
+    let name = match input.as_str() {
+        "c++" | "cpp" | "cxx" | "cc" => "c++",
+        "c#" | "cs" | "csharp" => "c#",
+        "py" | "python3" => "python",
+        "rs" => "rust",
+        "js" | "node" | "nodejs" => "javascript",
+        "sh" | "bash" | "shell" | "zsh" => "bourne again shell (bash)",
+        "golang" => "go",
+        "rb" => "ruby",
+        "yml" => "yaml",
+        "md" => "markdown",
+        "objc" => "objective-c",
+        other => other,
+    };
+
+    ps.syntaxes()
+        .iter()
+        .find(|syntax| syntax.name.to_lowercase() == name)
+        .or_else(|| {
+            ps.syntaxes().iter().find(|syntax| {
+                syntax
+                    .file_extensions
+                    .iter()
+                    .any(|extension| extension.to_lowercase() == name)
+            })
+        })
+}
+
+// Count the single-character insertions, deletions and substitutions needed to turn one string into another
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+// Replace every tab with spaces up to the next tab stop, so tabs take a known width inside a box,
+// wide characters count as two columns
+pub fn expand_tabs(text: &str, tab_width: usize) -> String {
+    if !text.contains('\t') {
+        return text.to_string();
+    }
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut column = 0;
+    for ch in text.chars() {
+        match ch {
+            '\t' if tab_width > 0 => {
+                let spaces = tab_width - column % tab_width;
+                expanded.push_str(&" ".repeat(spaces));
+                column += spaces;
+            }
+            '\t' => {}
+            '\n' => {
+                expanded.push(ch);
+                column = 0;
+            }
+            _ => {
+                expanded.push(ch);
+                column += ch.width().unwrap_or(0);
+            }
+        }
+    }
+    expanded
+}
+
+// Split a line into segments of at most the given widths without cutting through ANSI codes,
+// continuation segments start by re-applying the colors active at the wrap point
+pub fn wrap_ansi_line(line: &str, first_width: usize, rest_width: usize) -> Vec<String> {
+    let re = leading_ansi_code();
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+    let mut limit = first_width.max(1);
+    let mut active_codes = String::new();
+    let mut rest = line;
+
+    while let Some(ch) = rest.chars().next() {
+        // Copy escape sequences whole, remembering which colors are active
+        if let Some(code) = re.find(rest) {
+            if code.as_str() == "\x1b[0m" {
+                active_codes.clear();
+            } else {
+                active_codes.push_str(code.as_str());
+            }
+            current.push_str(code.as_str());
+            rest = &rest[code.end()..];
+            continue;
+        }
+
+        // Start a new segment once the next character no longer fits, wide characters take two cells
+        let ch_width = ch.width().unwrap_or(0);
+        if current_width > 0 && current_width + ch_width > limit {
+            if !active_codes.is_empty() {
+                current.push_str("\x1b[0m");
+            }
+            segments.push(std::mem::take(&mut current));
+            current.push_str(&active_codes);
+            current_width = 0;
+            limit = rest_width.max(1);
+        }
+
+        current.push(ch);
+        current_width += ch_width;
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    segments.push(current);
+    segments
+}
+
+// Show every occurrence of the terms in reverse video, ignoring case, on top of the colors already
+// in the line. Only escape codes are added, so the visible text and its width stay the same.
+pub fn mark_terms(line: &str, terms: &[String]) -> String {
+    let re = leading_ansi_code();
+
+    // Visible characters with their byte offset in the line, skipping escape sequences
+    let mut visible: Vec<(usize, char)> = Vec::new();
+    let mut offset = 0;
+    while offset < line.len() {
+        if let Some(code) = re.find(&line[offset..]) {
+            offset += code.end();
+            continue;
+        }
+        let ch = line[offset..].chars().next().unwrap();
+        visible.push((offset, ch));
+        offset += ch.len_utf8();
+    }
+
+    // Mark the visible characters covered by any term, overlapping matches simply merge
+    let fold = |ch: char| ch.to_lowercase().next().unwrap_or(ch);
+    let text: Vec<char> = visible.iter().map(|(_, ch)| fold(*ch)).collect();
+    let mut marked = vec![false; text.len()];
+    for term in terms {
+        let term: Vec<char> = term.trim().chars().map(fold).collect();
+        if term.is_empty() || term.len() > text.len() {
+            continue;
+        }
+        for start in 0..=text.len() - term.len() {
+            if text[start..start + term.len()] == term[..] {
+                marked[start..start + term.len()].fill(true);
+            }
+        }
+    }
+    if !marked.contains(&true) {
+        return line.to_string();
+    }
+
+    // Rebuild the line, opening reverse video before a marked run and closing it right after
+    let mut result = String::with_capacity(line.len() + 16);
+    let mut copied = 0;
+    for (index, (offset, ch)) in visible.iter().enumerate() {
+        let starts = marked[index] && (index == 0 || !marked[index - 1]);
+        if starts {
+            result.push_str(&line[copied..*offset]);
+            result.push_str("\x1b[7m");
+            copied = *offset;
+        }
+        let ends = marked[index] && (index + 1 == marked.len() || !marked[index + 1]);
+        if ends {
+            let end = offset + ch.len_utf8();
+            result.push_str(&line[copied..end]);
+            result.push_str("\x1b[27m");
+            copied = end;
+        }
+    }
+    result.push_str(&line[copied..]);
+    result
+}
+
+// Compile the pattern given to `view --grep`, keeping the regex crate's explanation of what is wrong
+pub fn compile_pattern(pattern: &str) -> Result<Regex, CodevaultError> {
+    Regex::new(pattern).map_err(|err| CodevaultError::InvalidPattern {
+        pattern: pattern.to_string(),
+        message: err.to_string(),
+    })
+}
+
+// Enable colors unless forced off, NO_COLOR is set, or stdout is not a terminal
+pub fn init_color(no_color: bool) {
+    let enabled = !no_color && !no_color_env() && std::io::stdout().is_terminal();
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+// Check whether colors are turned off through a non-empty NO_COLOR environment variable
+pub fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+pub fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+// Return the input unchanged when colors are enabled, otherwise strip all ANSI codes
+pub fn paint(input: &str) -> String {
+    if color_enabled() {
+        input.to_string()
+    } else {
+        strip_ansi_codes(input)
+    }
+}
+
+// Show timestamps relative to now for the rest of the run
+pub fn init_relative_time(relative: bool) {
+    RELATIVE_TIME.store(relative, Ordering::Relaxed);
+}
+
+// Drop the blank lines around headers, banners and boxes for the rest of the run
+pub fn init_compact(compact: bool) {
+    COMPACT.store(compact, Ordering::Relaxed);
+}
+
+pub fn compact() -> bool {
+    COMPACT.load(Ordering::Relaxed)
+}
+
+// Return the message without the blank lines padding it in compact mode, unchanged otherwise
+pub fn spaced(message: &str) -> &str {
+    if compact() {
+        message.trim_matches('\n')
+    } else {
+        message
+    }
+}
+
+// Settings and state of one run, built by the command line and passed to the library functions
+// that print, prompt or save the vault
+#[derive(Default)]
+pub struct Context {
+    // Settings read from the config file with the command-line flags applied
+    pub config: Config,
+    // Leave out decorative output such as headers, success banners and prompts
+    pub quiet: bool,
+    // Answer yes to every confirmation instead of asking
+    pub assume_yes: bool,
+    // Encrypt the vault when it is written, turned on as well once an encrypted vault is read
+    encrypt: AtomicBool,
+    // Passphrase of the encrypted vault, asked for at most once per run
+    passphrase: OnceLock<String>,
+    // Set once duplicate IDs have been reported, so a command loading the file twice only warns once
+    pub(crate) duplicates_reported: AtomicBool,
+}
+
+impl Context {
+    pub fn new(config: Config, quiet: bool, assume_yes: bool, encrypt: bool) -> Self {
+        Context {
+            config,
+            quiet,
+            assume_yes,
+            encrypt: AtomicBool::new(encrypt),
+            passphrase: OnceLock::new(),
+            duplicates_reported: AtomicBool::new(false),
+        }
+    }
+
+    // Print a decorative message such as a header or a success banner, unless running quietly
+    pub fn announce(&self, message: &str) {
+        if !self.quiet {
+            println!("{}", paint(spaced(message)));
+        }
+    }
+
+    // Like announce, but on stderr with the questions, for messages that are not the command's outcome
+    pub fn note(&self, message: &str) {
+        if !self.quiet {
+            eprintln!("{}", paint(spaced(message)));
+        }
+    }
+
+    // Keep the vault encrypted when it is saved, vaults that are already encrypted stay encrypted
+    pub fn enable_encryption(&self) {
+        self.encrypt.store(true, Ordering::Relaxed);
+    }
+
+    pub fn encryption_enabled(&self) -> bool {
+        self.encrypt.load(Ordering::Relaxed)
+    }
+
+    // Read the passphrase from CODEVAULT_PASSPHRASE or prompt for it, new passphrases are asked twice
+    fn passphrase(&self, confirm: bool) -> Result<&str, CodevaultError> {
+        if let Some(passphrase) = self.passphrase.get() {
+            return Ok(passphrase);
+        }
+
+        let passphrase = match std::env::var("CODEVAULT_PASSPHRASE") {
+            Ok(passphrase) if !passphrase.is_empty() => passphrase,
+            _ => {
+                let passphrase = rpassword::prompt_password("Vault passphrase: ")
+                    .map_err(CodevaultError::io("reading passphrase"))?;
+                if confirm
+                    && rpassword::prompt_password("Repeat passphrase: ")
+                        .map_err(CodevaultError::io("reading passphrase"))?
+                        != passphrase
+                {
+                    return Err(CodevaultError::Encryption(
+                        "the passphrases do not match".to_string(),
+                    ));
+                }
+                passphrase
+            }
+        };
+        if passphrase.is_empty() {
+            return Err(CodevaultError::Encryption(
+                "the passphrase must not be empty".to_string(),
+            ));
+        }
+
+        Ok(self.passphrase.get_or_init(|| passphrase))
+    }
+
+    // Encrypt the serialized vault with AES-256-GCM, using a fresh salt and nonce on every save
+    pub fn encrypt_vault(&self, plaintext: &[u8]) -> Result<Vec<u8>, CodevaultError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(self.passphrase(true)?, &salt)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| CodevaultError::Encryption("encrypting the vault failed".to_string()))?;
+
+        let mut content = ENCRYPTED_HEADER.to_vec();
+        content.extend_from_slice(&salt);
+        content.extend_from_slice(&nonce);
+        content.extend_from_slice(&ciphertext);
+        Ok(content)
+    }
+
+    // Decrypt an encrypted vault, a wrong passphrase fails authentication instead of producing garbage
+    pub fn decrypt_vault(&self, content: &[u8]) -> Result<Vec<u8>, CodevaultError> {
+        let body = &content[ENCRYPTED_HEADER.len()..];
+        if body.len() < SALT_LEN + NONCE_LEN {
+            return Err(CodevaultError::Encryption(
+                "the encrypted vault is truncated".to_string(),
+            ));
+        }
+        let (salt, rest) = body.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let key = derive_key(self.passphrase(false)?, salt)?;
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                CodevaultError::Encryption(
+                    "could not decrypt the vault, the passphrase is wrong or the file is damaged"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+// Check whether the file content is an encrypted vault rather than plain JSON
+pub fn is_encrypted(content: &[u8]) -> bool {
+    content.starts_with(ENCRYPTED_HEADER)
+}
+
+// Derive the 256-bit encryption key from the passphrase with Argon2
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], CodevaultError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| CodevaultError::Encryption(format!("deriving encryption key: {}", err)))?;
+    Ok(key)
+}
+
+// Test fixture shared by the tests of every module: a snippet with the given ID, tags and code,
+// created at a fixed time, without description or language
+#[cfg(test)]
+pub(crate) fn test_snippet(id: u32, tags: &[&str], code: &str) -> Snippet {
+    serde_json::from_value(serde_json::json!({
+        "tag": tags, "description": null, "code": code,
+        "timestamp": "2024-08-24T14:34:00+00:00", "language": null, "id": id
+    }))
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_language_accepts_aliases_and_any_case() {
+        for input in ["py", "python", "Python", " PYTHON "] {
+            assert_eq!(
+                resolve_language(input).map(|s| s.name.as_str()),
+                Some("Python")
+            );
+        }
+        for input in ["cpp", "c++", "C++"] {
+            assert_eq!(
+                resolve_language(input).map(|s| s.name.as_str()),
+                Some("C++")
+            );
+        }
+        for input in ["rs", "rust", "Rust"] {
+            assert_eq!(
+                resolve_language(input).map(|s| s.name.as_str()),
+                Some("Rust")
+            );
+        }
+    }
+
+    #[test]
+    fn expand_tabs_stops_at_the_next_tab_stop() {
+        assert_eq!(expand_tabs("\tx\n", 4), "    x\n");
+        assert_eq!(expand_tabs("ab\tc\n\td", 4), "ab  c\n    d");
+        assert_eq!(expand_tabs("你\tx", 4), "你  x");
+        assert_eq!(expand_tabs("a\tb", 0), "ab");
+    }
+
+    #[test]
+    fn line_endings_are_converted_without_a_byte_order_mark() {
+        let windows = "\u{feff}a\r\nb\r\n";
+        assert_eq!(LineEnding::Lf.apply(windows), "a\nb\n");
+        assert_eq!(LineEnding::Crlf.apply("a\nb\r\n"), "a\r\nb\r\n");
+        assert_eq!(LineEnding::Keep.apply(windows), windows);
+    }
+
+    #[test]
+    fn mark_terms_only_adds_escape_codes_around_matches() {
+        let line = "\x1b[38;2;1;2;3mlet \x1b[38;2;4;5;6mFooBar\x1b[0m = foo;";
+        let marked = mark_terms(line, &["foo".to_string(), "obar".to_string()]);
+
+        assert_eq!(strip_ansi_codes(&marked), strip_ansi_codes(line));
+        assert_eq!(
+            marked,
+            "\x1b[38;2;1;2;3mlet \x1b[38;2;4;5;6m\x1b[7mFooBar\x1b[27m\x1b[0m = \x1b[7mfoo\x1b[27m;"
+        );
+        assert_eq!(mark_terms(line, &["baz".to_string()]), line);
+    }
+
+    #[test]
+    fn normalize_tags_merges_tags_that_differ_in_case() {
+        let mut snippet = test_snippet(1, &["Rust", "RUST", "web-API"], "");
+
+        assert!(snippet.normalize_tags(TagCase::Title));
+        assert_eq!(snippet.tag, vec!["Rust", "Web-Api"]);
+        assert!(snippet.normalize_tags(TagCase::Lower));
+        assert_eq!(snippet.tag, vec!["rust", "web-api"]);
+        assert!(!snippet.normalize_tags(TagCase::Lower));
+    }
+
+    #[test]
+    fn retag_trims_and_merges_with_an_existing_tag() {
+        let mut snippet = test_snippet(1, &["old", "Web", "api"], "");
+
+        assert!(snippet.retag(" OLD ", " new"));
+        assert_eq!(snippet.tag, vec!["new", "Web", "api"]);
+        assert!(snippet.retag("web", "web"));
+        assert_eq!(snippet.tag, vec!["new", "web", "api"]);
+        assert!(snippet.retag("api", " NEW "));
+        assert_eq!(snippet.tag, vec!["new", "web"]);
+        assert!(!snippet.retag("missing", "x"));
+    }
+
+    #[test]
+    fn remember_version_keeps_the_newest_versions_first() {
+        let mut snippet = test_snippet(1, &[], "v0");
+        for version in 1..=HISTORY_LIMIT + 2 {
+            let previous = snippet.clone();
+            snippet.code = format!("v{}", version);
+            snippet.remember_version(&previous);
+        }
+
+        // Unchanged code is not a new version
+        let previous = snippet.clone();
+        snippet.remember_version(&previous);
+
+        assert_eq!(snippet.history.len(), HISTORY_LIMIT);
+        assert_eq!(snippet.history[0].code, format!("v{}", HISTORY_LIMIT + 1));
+        assert_eq!(snippet.history[HISTORY_LIMIT - 1].code, "v2");
+    }
+
+    #[test]
+    fn relative_timestamp_uses_the_largest_whole_unit() {
+        let now = Local::now();
+        let ago = |duration: chrono::Duration| relative_timestamp(&(now - duration), &now);
+
+        assert_eq!(ago(chrono::Duration::seconds(20)), "just now");
+        assert_eq!(ago(chrono::Duration::seconds(-20)), "just now");
+        assert_eq!(ago(chrono::Duration::minutes(1)), "1 minute ago");
+        assert_eq!(ago(chrono::Duration::hours(5)), "5 hours ago");
+        assert_eq!(ago(chrono::Duration::days(3)), "3 days ago");
+        assert_eq!(ago(chrono::Duration::days(65)), "2 months ago");
+        assert_eq!(ago(chrono::Duration::days(800)), "2 years ago");
+    }
+
+    #[test]
+    fn nested_tags_match_their_parents_and_count_once_per_level() {
+        let snippets = vec![
+            test_snippet(1, &["web/frontend/react", "web / frontend"], "a"),
+            test_snippet(2, &["web/backend", "webpack"], "b"),
+        ];
+
+        assert!(snippets[0].matches_tags("web", false));
+        assert!(snippets[0].matches_tags("Web/Frontend", false));
+        assert!(!snippets[0].matches_tags("web/front", false));
+        assert!(!snippets[0].matches_tags("webpack", false));
+        assert!(snippets[1].matches_tags("WEBPACK", false));
+        assert!(!snippets[0].matches_tags("web", true));
+        assert!(snippets[0].matches_tags("web/frontend", true));
+        assert!(snippets[0].matches_tags(" web/frontend/React ", true));
+
+        let tree = TagTree::from_snippets(&snippets);
+        assert_eq!(tree.count, 2);
+        assert_eq!(tree.children["web"].count, 2);
+        assert_eq!(tree.children["web"].children["frontend"].count, 1);
+        assert_eq!(
+            tree.children["web"].children["frontend"].children["react"].count,
+            1
+        );
+        assert_eq!(tree.children["webpack"].count, 1);
+    }
+
+    #[test]
+    fn resolve_language_rejects_unknown_languages() {
+        assert!(resolve_language("pyton").is_none());
+        assert!(resolve_language("").is_none());
+    }
+}
@@ -0,0 +1,103 @@
+// Named styles for the terminal output, so each kind of text is colored in one place.
+// Every helper returns plain text when colors are disabled with `--no-color` or `NO_COLOR`.
+use std::fmt::Display;
+
+use clap::builder::styling::{Ansi256Color, AnsiColor, Color, Style};
+
+use crate::models::color_enabled;
+
+// Section titles, e.g. "Snippets Collection:"
+pub const HEADER: Style = Style::new()
+    .fg_color(Some(Color::Ansi256(Ansi256Color(201))))
+    .bold();
+// Names of the fields shown in a snippet box, e.g. "ID:"
+pub const LABEL: Style = Style::new()
+    .fg_color(Some(Color::Ansi(AnsiColor::Yellow)))
+    .bold();
+// Values of the fields shown in a snippet box
+pub const VALUE: Style = Style::new()
+    .fg_color(Some(Color::Ansi(AnsiColor::Magenta)))
+    .bold();
+// Messages about something that went wrong
+pub const ERROR: Style = Style::new()
+    .fg_color(Some(Color::Ansi(AnsiColor::Red)))
+    .bold();
+// The lines drawn around boxes
+pub const BORDER: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Blue)));
+// Questions and instructions for the user
+pub const PROMPT: Style = Style::new()
+    .fg_color(Some(Color::Ansi(AnsiColor::Cyan)))
+    .bold();
+
+// Lines taken out and put in by a change, and the headers of the changed parts, in `diff`
+pub const REMOVED: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red)));
+pub const ADDED: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+pub const HUNK: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
+
+// Function to wrap text in a style, leaving it plain when colors are disabled
+pub fn styled(style: Style, text: impl Display) -> String {
+    if color_enabled() {
+        format!("{}{}{}", style.render(), text, style.render_reset())
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn header(text: impl Display) -> String {
+    styled(HEADER, text)
+}
+
+pub fn label(text: impl Display) -> String {
+    styled(LABEL, text)
+}
+
+pub fn value(text: impl Display) -> String {
+    styled(VALUE, text)
+}
+
+pub fn error(text: impl Display) -> String {
+    styled(ERROR, text)
+}
+
+pub fn border(text: impl Display) -> String {
+    styled(BORDER, text)
+}
+
+pub fn prompt(text: impl Display) -> String {
+    styled(PROMPT, text)
+}
+
+pub fn removed(text: impl Display) -> String {
+    styled(REMOVED, text)
+}
+
+pub fn added(text: impl Display) -> String {
+    styled(ADDED, text)
+}
+
+pub fn hunk(text: impl Display) -> String {
+    styled(HUNK, text)
+}
+
+// Colors a language name can get, each language always gets the same one
+const LANGUAGE_PALETTE: [AnsiColor; 6] = [
+    AnsiColor::Green,
+    AnsiColor::Cyan,
+    AnsiColor::Red,
+    AnsiColor::BrightBlue,
+    AnsiColor::BrightYellow,
+    AnsiColor::BrightMagenta,
+];
+
+// Function to color a language name, picking its color from a hash of the name so it is stable between runs
+pub fn language(name: &str) -> String {
+    // FNV-1a over the lowercased name, so "rust" and "Rust" look the same
+    let hash = name
+        .to_lowercase()
+        .bytes()
+        .fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+    let color = LANGUAGE_PALETTE[hash as usize % LANGUAGE_PALETTE.len()];
+    styled(Style::new().fg_color(Some(Color::Ansi(color))).bold(), name)
+}